@@ -1,10 +1,11 @@
 use math::*;
-use miniquad::gl::*;
 use ogt::*;
 use quad_rand as rnd;
 
 struct Stage {
     sprite_batch: SpriteBatch,
+    /// Set with `--instanced`
+    instanced_batch: Option<InstancedSpriteBatch>,
     shape_batch: ShapeBatch,
     font: Font,
     bunnies: Vec<Bunny>,
@@ -14,7 +15,7 @@ struct Stage {
 impl Application for Stage {
     fn render(&mut self, ctx: &Context, delta: f32) {
         if ctx.is_mouse_button_pressed(MouseButton::Left) {
-            for i in 0..10_000 {
+            for _ in 0..10_000 {
                 self.bunnies.push(Bunny::new(
                     10.0,
                     Color::rgb(255, 255, 0),
@@ -54,24 +55,47 @@ impl Application for Stage {
         self.sprite_batch.set_model_view_projection_matrix(mvp);
         self.shape_batch.set_model_view_projection_matrix(mvp);
 
-        self.sprite_batch.set_texture(self.tex_bunny.clone());
-        for bunny in self.bunnies.iter() {
-            self.sprite_batch.add(
-                ctx,
-                Region {
-                    top_left: [0.0, 0.0],
-                    bottom_right: [self.tex_bunny.width as f32, self.tex_bunny.height as f32],
-                },
-                bunny.tint,
-                vec2(13.0, 19.0),
-                vec2(bunny.x, bunny.y), // Affine2::from_angle_translation(bunny.rot, vec2(bunny.x, bunny.y)),
-            );
+        let region = self.tex_bunny.as_region();
+        // Bunny positions are the bottom left corner. A Vec2 transform ignores the origin, the
+        // instanced batch puts the origin at the position, so it gets shifted by the origin.
+        let origin = vec2(13.0, 19.0);
+        if let Some(instanced_batch) = &mut self.instanced_batch {
+            instanced_batch.set_model_view_projection_matrix(mvp);
+            for bunny in self.bunnies.iter() {
+                instanced_batch.add(
+                    ctx,
+                    &self.tex_bunny,
+                    region,
+                    bunny.tint,
+                    origin,
+                    vec2(bunny.x, bunny.y) + origin,
+                    0.0,
+                    Vec2::ONE,
+                );
+            }
+            instanced_batch.draw(ctx);
+        } else {
+            for bunny in self.bunnies.iter() {
+                self.sprite_batch.add(
+                    ctx,
+                    &self.tex_bunny,
+                    region,
+                    bunny.tint,
+                    origin,
+                    vec2(bunny.x, bunny.y), // Affine2::from_angle_translation(bunny.rot, vec2(bunny.x, bunny.y)),
+                );
+            }
+            self.sprite_batch.draw(ctx);
         }
-        self.sprite_batch.draw(ctx);
         self.font.draw_text(
             ctx,
             &mut self.sprite_batch,
-            &format!("FPS: {:.2}, #b: {}", 1.0 / delta, self.bunnies.len()),
+            &format!(
+                "FPS: {:.2}, max: {:.1}ms, #b: {}",
+                ctx.frame_stats().fps(),
+                ctx.frame_stats().max_frame_time() * 1000.0,
+                self.bunnies.len()
+            ),
             vec2(20.0, 20.0),
             WHITE,
         );
@@ -134,7 +158,7 @@ impl Bunny {
 }
 
 fn main() {
-    go(|ctx| {
+    go_with_config(WindowConfig::new().title("bunnymark"), |ctx| {
         let image = image::load_from_memory(include_bytes!("ogt_thing.png"))
             .unwrap()
             .to_rgba8();
@@ -155,8 +179,10 @@ fn main() {
         }
 
         let font = LoadedFont::from_bytes(include_bytes!("Hack-Regular.ttf"));
+        let instanced = std::env::args().any(|arg| arg == "--instanced");
         Stage {
             sprite_batch,
+            instanced_batch: instanced.then(|| InstancedSpriteBatch::new(ctx)),
             shape_batch: ShapeBatch::new(ctx),
             bunnies,
             font: font.create_font(ctx, 32.0),
@@ -0,0 +1,556 @@
+use crate::input::KEY_CODES;
+use crate::{Context, GamepadAxis, GamepadButton, KeyCode, MouseButton, OgtError};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Analog gamepad values beyond this count as a pressed button.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WheelDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WheelAxis {
+    Horizontal,
+    Vertical,
+}
+
+/// A digital input an action can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Down in every frame the wheel moves in that direction.
+    Wheel(WheelDirection),
+    GamepadButton(GamepadButton),
+    /// Down while the axis is pushed past the half way point, positive (`true`) or negative direction.
+    GamepadAxis(GamepadAxis, bool),
+}
+
+/// An input contributing to a named axis. Axis values are clamped to -1..1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AxisBinding {
+    /// -1 while `negative` is down, 1 while `positive` is down.
+    Buttons {
+        negative: Binding,
+        positive: Binding,
+    },
+    GamepadAxis(GamepadAxis),
+    Wheel(WheelAxis),
+}
+
+impl Binding {
+    fn is_down(&self, ctx: &Context) -> bool {
+        match *self {
+            Binding::Key(key) => ctx.is_key_down(key),
+            Binding::Mouse(button) => ctx.is_mouse_button_down(button),
+            Binding::Wheel(direction) => {
+                let wheel = ctx.mouse_wheel();
+                match direction {
+                    WheelDirection::Up => wheel.y > 0.0,
+                    WheelDirection::Down => wheel.y < 0.0,
+                    WheelDirection::Left => wheel.x < 0.0,
+                    WheelDirection::Right => wheel.x > 0.0,
+                }
+            }
+            Binding::GamepadButton(button) => ctx
+                .gamepads()
+                .pads()
+                .iter()
+                .any(|pad| pad.is_button_down(button)),
+            Binding::GamepadAxis(axis, positive) => ctx.gamepads().pads().iter().any(|pad| {
+                let value = pad.axis(axis);
+                if positive {
+                    value >= AXIS_PRESS_THRESHOLD
+                } else {
+                    value <= -AXIS_PRESS_THRESHOLD
+                }
+            }),
+        }
+    }
+
+    /// Went down this frame, even if it was released again before the frame ended. Wheel and
+    /// analog bindings only have the frame they turn down in, see [`Binding::is_down`].
+    fn is_pressed(&self, ctx: &Context) -> bool {
+        match *self {
+            Binding::Key(key) => ctx.is_key_pressed(key),
+            Binding::Mouse(button) => ctx.is_mouse_button_pressed(button),
+            Binding::GamepadButton(button) => ctx
+                .gamepads()
+                .pads()
+                .iter()
+                .any(|pad| pad.is_button_pressed(button)),
+            Binding::Wheel(_) | Binding::GamepadAxis(..) => false,
+        }
+    }
+
+    /// Went up this frame, even if it was pressed again before the frame ended.
+    fn is_released(&self, ctx: &Context) -> bool {
+        match *self {
+            Binding::Key(key) => ctx.is_key_released(key),
+            Binding::Mouse(button) => ctx.is_mouse_button_released(button),
+            Binding::GamepadButton(button) => ctx
+                .gamepads()
+                .pads()
+                .iter()
+                .any(|pad| pad.is_button_released(button)),
+            Binding::Wheel(_) | Binding::GamepadAxis(..) => false,
+        }
+    }
+}
+
+impl AxisBinding {
+    fn value(&self, ctx: &Context) -> f32 {
+        match *self {
+            AxisBinding::Buttons { negative, positive } => {
+                positive.is_down(ctx) as i32 as f32 - negative.is_down(ctx) as i32 as f32
+            }
+            AxisBinding::GamepadAxis(axis) => ctx
+                .gamepads()
+                .pads()
+                .iter()
+                .map(|pad| pad.axis(axis))
+                .fold(0.0, |a: f32, b| if b.abs() > a.abs() { b } else { a }),
+            AxisBinding::Wheel(WheelAxis::Horizontal) => ctx.mouse_wheel().x,
+            AxisBinding::Wheel(WheelAxis::Vertical) => ctx.mouse_wheel().y,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Action {
+    bindings: Vec<Binding>,
+    down: bool,
+    pressed: bool,
+    released: bool,
+}
+
+#[derive(Default)]
+struct Axis {
+    bindings: Vec<AxisBinding>,
+    value: f32,
+}
+
+/// Maps named actions and axes to concrete inputs. Call [`ActionMap::update`] once per frame,
+/// then query by name. Gamepad bindings react to every connected pad.
+#[derive(Default)]
+pub struct ActionMap {
+    actions: BTreeMap<String, Action>,
+    axes: BTreeMap<String, Axis>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Panics if `action` can't be saved, see [`ActionMap::save`].
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        assert_valid_name(action);
+        let bindings = &mut self.actions.entry(action.to_string()).or_default().bindings;
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(action) = self.actions.get_mut(action) {
+            action.bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Replaces all bindings of `action`.
+    pub fn rebind(&mut self, action: &str, bindings: impl IntoIterator<Item = Binding>) {
+        assert_valid_name(action);
+        self.actions.entry(action.to_string()).or_default().bindings =
+            bindings.into_iter().collect();
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions
+            .get(action)
+            .map_or(&[], |action| &action.bindings[..])
+    }
+
+    /// Panics if `axis` can't be saved, see [`ActionMap::save`].
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        assert_valid_name(axis);
+        let bindings = &mut self.axes.entry(axis.to_string()).or_default().bindings;
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        if let Some(axis) = self.axes.get_mut(axis) {
+            axis.bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Replaces all bindings of `axis`.
+    pub fn rebind_axis(&mut self, axis: &str, bindings: impl IntoIterator<Item = AxisBinding>) {
+        assert_valid_name(axis);
+        self.axes.entry(axis.to_string()).or_default().bindings = bindings.into_iter().collect();
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], |axis| &axis.bindings[..])
+    }
+
+    pub fn update(&mut self, ctx: &Context) {
+        for action in self.actions.values_mut() {
+            let down = action.bindings.iter().any(|binding| binding.is_down(ctx));
+            // Taps shorter than a frame never show up as down
+            action.pressed = down && !action.down
+                || action
+                    .bindings
+                    .iter()
+                    .any(|binding| binding.is_pressed(ctx));
+            action.released = !down && action.down
+                || action
+                    .bindings
+                    .iter()
+                    .any(|binding| binding.is_released(ctx));
+            action.down = down;
+        }
+        for axis in self.axes.values_mut() {
+            let value: f32 = axis.bindings.iter().map(|binding| binding.value(ctx)).sum();
+            axis.value = value.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn down(&self, action: &str) -> bool {
+        self.actions.get(action).is_some_and(|action| action.down)
+    }
+
+    pub fn pressed(&self, action: &str) -> bool {
+        self.actions
+            .get(action)
+            .is_some_and(|action| action.pressed)
+    }
+
+    pub fn released(&self, action: &str) -> bool {
+        self.actions
+            .get(action)
+            .is_some_and(|action| action.released)
+    }
+
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.axes.get(axis).map_or(0.0, |axis| axis.value)
+    }
+
+    /// Serializes all bindings in a simple line based format, e.g.
+    /// ```text
+    /// [actions]
+    /// jump = key:Space, gamepad:South
+    /// [axes]
+    /// move_x = key:A/key:D, gamepad_axis:LeftX
+    /// ```
+    /// Names can't be empty, contain `=`, `,` or line breaks, start with `#` or `[` or have
+    /// surrounding whitespace, binding such names panics.
+    pub fn save(&self) -> String {
+        let mut result = String::from("[actions]\n");
+        for (name, action) in self.actions.iter() {
+            result.push_str(&format!("{} = {}\n", name, join(&action.bindings)));
+        }
+        result.push_str("[axes]\n");
+        for (name, axis) in self.axes.iter() {
+            result.push_str(&format!("{} = {}\n", name, join(&axis.bindings)));
+        }
+        result
+    }
+
+    /// Reads bindings written by [`ActionMap::save`]. Every action or axis listed replaces the
+    /// current bindings of that name, others are kept.
+    pub fn load(&mut self, settings: &str) -> Result<(), OgtError> {
+        let mut in_axes = false;
+        for (line_number, line) in settings.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: String| {
+                OgtError::InvalidData(format!("Line {}: {}", line_number + 1, message))
+            };
+            match line {
+                "" => continue,
+                _ if line.starts_with('#') => continue,
+                "[actions]" => in_axes = false,
+                "[axes]" => in_axes = true,
+                _ => {
+                    let (name, bindings) = line
+                        .split_once('=')
+                        .ok_or_else(|| error(format!("Expected 'name = bindings': {}", line)))?;
+                    let name = name.trim();
+                    check_name(name).map_err(error)?;
+                    let bindings = bindings
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|binding| !binding.is_empty());
+                    if in_axes {
+                        let bindings = bindings
+                            .map(AxisBinding::from_str)
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(error)?;
+                        self.rebind_axis(name, bindings);
+                    } else {
+                        let bindings = bindings
+                            .map(Binding::from_str)
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(error)?;
+                        self.rebind(name, bindings);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.trim() != name
+        || name.starts_with(['#', '['])
+        || name.contains(['=', ',', '\n', '\r'])
+    {
+        return Err(format!("Invalid name {:?}", name));
+    }
+    Ok(())
+}
+
+fn assert_valid_name(name: &str) {
+    if let Err(err) = check_name(name) {
+        panic!("{}", err);
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn parse_named<T: fmt::Debug + Copy>(all: &[T], name: &str) -> Result<T, String> {
+    all.iter()
+        .copied()
+        .find(|item| format!("{:?}", item) == name)
+        .ok_or_else(|| format!("Unknown input '{}'", name))
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "key:{:?}", key),
+            Binding::Mouse(button) => write!(f, "mouse:{:?}", button),
+            Binding::Wheel(direction) => write!(f, "wheel:{:?}", direction),
+            Binding::GamepadButton(button) => write!(f, "gamepad:{:?}", button),
+            Binding::GamepadAxis(axis, positive) => {
+                write!(
+                    f,
+                    "gamepad_axis:{}{:?}",
+                    if *positive { '+' } else { '-' },
+                    axis
+                )
+            }
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, name) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Expected 'kind:name': {}", s))?;
+        Ok(match kind {
+            "key" => Binding::Key(parse_named(KEY_CODES, name)?),
+            "mouse" => Binding::Mouse(parse_named(
+                &[MouseButton::Left, MouseButton::Right, MouseButton::Middle],
+                name,
+            )?),
+            "wheel" => Binding::Wheel(parse_named(
+                &[
+                    WheelDirection::Up,
+                    WheelDirection::Down,
+                    WheelDirection::Left,
+                    WheelDirection::Right,
+                ],
+                name,
+            )?),
+            "gamepad" => Binding::GamepadButton(parse_named(&GamepadButton::ALL, name)?),
+            "gamepad_axis" => {
+                let positive = match name.chars().next() {
+                    Some('+') => true,
+                    Some('-') => false,
+                    _ => return Err(format!("Expected '+' or '-' before axis: {}", s)),
+                };
+                Binding::GamepadAxis(parse_named(&GamepadAxis::ALL, &name[1..])?, positive)
+            }
+            _ => return Err(format!("Unknown input kind '{}'", kind)),
+        })
+    }
+}
+
+impl fmt::Display for AxisBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AxisBinding::Buttons { negative, positive } => write!(f, "{}/{}", negative, positive),
+            AxisBinding::GamepadAxis(axis) => write!(f, "gamepad_axis:{:?}", axis),
+            AxisBinding::Wheel(axis) => write!(f, "wheel:{:?}", axis),
+        }
+    }
+}
+
+impl FromStr for AxisBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((negative, positive)) = s.split_once('/') {
+            return Ok(AxisBinding::Buttons {
+                negative: negative.trim().parse()?,
+                positive: positive.trim().parse()?,
+            });
+        }
+        match s.split_once(':') {
+            Some(("gamepad_axis", name)) => Ok(AxisBinding::GamepadAxis(parse_named(
+                &GamepadAxis::ALL,
+                name,
+            )?)),
+            Some(("wheel", name)) => Ok(AxisBinding::Wheel(parse_named(
+                &[WheelAxis::Horizontal, WheelAxis::Vertical],
+                name,
+            )?)),
+            _ => Err(format!("Unknown axis input '{}'", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let mut actions = ActionMap::new();
+        actions.bind("jump", Binding::Key(KeyCode::Space));
+        actions.bind("jump", Binding::GamepadButton(GamepadButton::South));
+        actions.bind("fire", Binding::Mouse(MouseButton::Left));
+        actions.bind(
+            "fire",
+            Binding::GamepadAxis(GamepadAxis::RightTrigger, true),
+        );
+        actions.bind("zoom_in", Binding::Wheel(WheelDirection::Up));
+        actions.bind_axis(
+            "move_x",
+            AxisBinding::Buttons {
+                negative: Binding::Key(KeyCode::A),
+                positive: Binding::Key(KeyCode::D),
+            },
+        );
+        actions.bind_axis("move_x", AxisBinding::GamepadAxis(GamepadAxis::LeftX));
+        actions.bind_axis("zoom", AxisBinding::Wheel(WheelAxis::Vertical));
+
+        let settings = actions.save();
+        let mut loaded = ActionMap::new();
+        loaded.load(&settings).unwrap();
+        assert_eq!(loaded.save(), settings);
+        assert_eq!(
+            loaded.bindings("jump"),
+            &[
+                Binding::Key(KeyCode::Space),
+                Binding::GamepadButton(GamepadButton::South)
+            ]
+        );
+        assert_eq!(loaded.axis_bindings("move_x").len(), 2);
+    }
+
+    #[test]
+    fn names_survive_save_and_load() {
+        let mut actions = ActionMap::new();
+        for name in ["move left", "jump#2", "a[0]", "ü/ä:x"] {
+            actions.bind(name, Binding::Key(KeyCode::Space));
+            actions.bind_axis(name, AxisBinding::Wheel(WheelAxis::Vertical));
+        }
+        let settings = actions.save();
+        let mut loaded = ActionMap::new();
+        loaded.load(&settings).unwrap();
+        assert_eq!(loaded.save(), settings);
+        assert_eq!(
+            loaded.bindings("move left"),
+            &[Binding::Key(KeyCode::Space)]
+        );
+
+        for name in ["", "a=b", "a,b", "a\nb", "#a", "[a]", " a", "a "] {
+            assert!(check_name(name).is_err(), "{name:?}");
+        }
+        assert!(loaded.load("[actions]\n[a] = key:Space").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid name")]
+    fn bind_rejects_invalid_names() {
+        ActionMap::new().bind("jump = now", Binding::Key(KeyCode::Space));
+    }
+
+    #[test]
+    fn load_reports_errors() {
+        let mut actions = ActionMap::new();
+        assert!(actions.load("[actions]\njump = key:Spacebar").is_err());
+        assert!(actions.load("jump key:Space").is_err());
+        assert!(actions
+            .load("# comment\n[actions]\njump = key:Space\n")
+            .is_ok());
+    }
+
+    #[test]
+    fn pressed_down_released() {
+        let mut ctx = Context::default();
+        let mut actions = ActionMap::new();
+        actions.bind("jump", Binding::Key(KeyCode::Space));
+        actions.bind_axis(
+            "move_x",
+            AxisBinding::Buttons {
+                negative: Binding::Key(KeyCode::A),
+                positive: Binding::Key(KeyCode::Space),
+            },
+        );
+
+        ctx.keys.entry(KeyCode::Space).or_default().down = true;
+        actions.update(&ctx);
+        assert!(actions.pressed("jump") && actions.down("jump"));
+        assert_eq!(actions.axis("move_x"), 1.0);
+
+        actions.update(&ctx);
+        assert!(!actions.pressed("jump") && actions.down("jump"));
+
+        ctx.keys.entry(KeyCode::Space).or_default().down = false;
+        actions.update(&ctx);
+        assert!(actions.released("jump") && !actions.down("jump"));
+        assert!(!actions.pressed("unknown"));
+    }
+
+    #[test]
+    fn tap_within_one_frame() {
+        let mut ctx = Context::default();
+        let mut actions = ActionMap::new();
+        actions.bind("jump", Binding::Key(KeyCode::Space));
+        actions.update(&ctx);
+
+        let key = ctx.keys.entry(KeyCode::Space).or_default();
+        key.pressed = true;
+        key.released = true;
+        actions.update(&ctx);
+        assert!(actions.pressed("jump") && actions.released("jump"));
+        assert!(!actions.down("jump"));
+
+        ctx.end_frame();
+        actions.update(&ctx);
+        assert!(!actions.pressed("jump") && !actions.released("jump"));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::backend::load_file;
-use crate::{Context, Font, LoadedFont, Texture, TextureBuilder};
+use crate::{Context, Font, LoadedFont, OgtError, Texture, TextureBuilder};
 use std::cell::{RefCell, RefMut};
 use std::rc::Rc;
 
@@ -9,7 +9,7 @@ impl<T> std::ops::Deref for Ref<'_, T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
-        &*self.0
+        &self.0
     }
 }
 
@@ -17,20 +17,32 @@ enum Inner<T, D> {
     Empty,
     Loaded(D),
     Ready(T),
+    Failed(OgtError),
 }
 
-impl<T> Inner<T, Vec<u8>> {
-    fn try_readying(&mut self, build: impl FnOnce(&Vec<u8>) -> T) {
-        match self {
-            Inner::Empty | Inner::Ready(_) => (),
-            Inner::Loaded(data) => {
-                let built = build(data);
-                *self = Inner::Ready(built);
-            }
+impl<T, D> Inner<T, D> {
+    fn try_readying(&mut self, build: impl FnOnce(&D) -> Result<T, OgtError>) {
+        if let Inner::Loaded(data) = self {
+            *self = match build(data) {
+                Ok(built) => Inner::Ready(built),
+                Err(err) => Inner::Failed(err),
+            };
         }
     }
 }
 
+fn ready<T, D>(inner: RefMut<'_, Inner<T, D>>) -> Result<Option<Ref<'_, T>>, OgtError> {
+    match *inner {
+        Inner::Empty => Ok(None),
+        Inner::Loaded(_) => unreachable!(),
+        Inner::Failed(ref err) => Err(err.clone()),
+        Inner::Ready(_) => Ok(Some(Ref(RefMut::map(inner, |item| match item {
+            Inner::Ready(item) => item,
+            _ => unreachable!(),
+        })))),
+    }
+}
+
 #[derive(Clone)]
 pub struct Asset<T, D = Vec<u8>> {
     inner: Rc<RefCell<Inner<T, D>>>,
@@ -44,22 +56,17 @@ impl<T: 'static> Asset<T> {
             Ok(content) => {
                 *loader.borrow_mut() = Inner::Loaded(content);
             }
-            Err(err) => eprintln!("Could not load {:?}", err),
+            Err(err) => {
+                *loader.borrow_mut() = Inner::Failed(err);
+            }
         });
         Asset { inner }
     }
 
-    fn get_or_build(&self, build: impl FnOnce(&Vec<u8>) -> T) -> Option<Ref<T>> {
-        let mut inner = self.inner.borrow_mut();
-        inner.try_readying(build);
-        match *inner {
-            Inner::Empty => None,
-            Inner::Loaded(_) => unreachable!(),
-            Inner::Ready(_) => Some(Ref(RefMut::map(inner, |item| match item {
-                Inner::Ready(item) => item,
-                _ => unreachable!(),
-            }))),
-        }
+    /// Creates an asset from data that is already in memory.
+    pub fn from_bytes(data: Vec<u8>) -> Asset<T> {
+        let inner = Rc::new(RefCell::new(Inner::Loaded(data)));
+        Asset { inner }
     }
 }
 
@@ -68,24 +75,43 @@ impl<T, D> Asset<T, D> {
         let mut inner = self.inner.borrow_mut();
         let inner = std::mem::replace(&mut *inner, Inner::Empty);
         match inner {
-            Inner::Empty | Inner::Loaded(_) => None,
+            Inner::Empty | Inner::Loaded(_) | Inner::Failed(_) => None,
             Inner::Ready(result) => Some(result),
         }
     }
 }
 
 impl Asset<Texture> {
-    pub fn get(&self, ctx: &Context) -> Option<Ref<Texture>> {
-        self.get_or_build(|content| {
-            let image = image::load_from_memory(&content).unwrap().to_rgba8();
-            TextureBuilder::from_bytes(image.as_raw(), image.width(), image.height()).build(ctx)
-        })
+    /// `None` while loading or if loading failed, see [`Asset::try_get`].
+    pub fn get(&self, ctx: &Context) -> Option<Ref<'_, Texture>> {
+        self.try_get(ctx).ok().flatten()
+    }
+
+    /// `Ok(None)` while loading, the error if the file couldn't be read or decoded.
+    pub fn try_get(&self, ctx: &Context) -> Result<Option<Ref<'_, Texture>>, OgtError> {
+        let mut inner = self.inner.borrow_mut();
+        inner.try_readying(|content| {
+            let image = image::load_from_memory(content)?.to_rgba8();
+            Ok(
+                TextureBuilder::from_bytes(image.as_raw(), image.width(), image.height())
+                    .build(ctx),
+            )
+        });
+        ready(inner)
     }
 }
 
 impl Asset<LoadedFont> {
-    pub fn get(&self, _ctx: &Context) -> Option<Ref<LoadedFont>> {
-        self.get_or_build(|content| LoadedFont::from_bytes(content))
+    /// `None` while loading or if loading failed, see [`Asset::try_get`].
+    pub fn get(&self, ctx: &Context) -> Option<Ref<'_, LoadedFont>> {
+        self.try_get(ctx).ok().flatten()
+    }
+
+    /// `Ok(None)` while loading, the error if the file couldn't be read or parsed.
+    pub fn try_get(&self, _ctx: &Context) -> Result<Option<Ref<'_, LoadedFont>>, OgtError> {
+        let mut inner = self.inner.borrow_mut();
+        inner.try_readying(|content| LoadedFont::try_from_bytes(content));
+        ready(inner)
     }
 }
 
@@ -100,24 +126,42 @@ impl Asset<Font, FontSource> {
         Self { inner }
     }
 
-    pub fn get(&self, ctx: &Context) -> Option<Ref<Font>> {
+    /// `None` while the source is loading or if anything failed, see [`Asset::try_get`].
+    pub fn get(&self, ctx: &Context) -> Option<Ref<'_, Font>> {
+        self.try_get(ctx).ok().flatten()
+    }
+
+    pub fn try_get(&self, ctx: &Context) -> Result<Option<Ref<'_, Font>>, OgtError> {
         let mut inner = self.inner.borrow_mut();
-        match *inner {
-            Inner::Empty => {
-                return None;
-            }
-            Inner::Loaded(FontSource { ref source, size }) => {
-                if let Some(font) = source.get(ctx).map(|fnt| fnt.create_font(ctx, size)) {
-                    *inner = Inner::Ready(font);
-                } else {
-                    return None;
-                }
-            }
-            Inner::Ready(_) => {}
+        if let Inner::Loaded(FontSource { ref source, size }) = *inner {
+            let font = match source.try_get(ctx) {
+                Ok(Some(font)) => font.try_create_font(ctx, size),
+                Ok(None) => return Ok(None),
+                Err(err) => Err(err),
+            };
+            inner.try_readying(|_| font);
         }
-        Some(Ref(RefMut::map(inner, |item| match item {
-            Inner::Ready(item) => item,
-            _ => unreachable!(),
-        })))
+        ready(inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn corrupt_data_fails_instead_of_panicking() {
+        let ctx = Context::default();
+        let texture: Asset<Texture> = Asset::from_bytes(b"not an image".to_vec());
+        assert!(matches!(texture.try_get(&ctx), Err(OgtError::Image(_))));
+        assert!(texture.get(&ctx).is_none());
+
+        let font: Asset<LoadedFont> = Asset::from_bytes(vec![0; 16]);
+        assert!(matches!(font.try_get(&ctx), Err(OgtError::Font(_))));
+        let sized = Asset::<Font, FontSource>::new(FontSource {
+            size: 16.0,
+            source: font,
+        });
+        assert!(matches!(sized.try_get(&ctx), Err(OgtError::Font(_))));
     }
 }
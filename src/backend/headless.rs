@@ -0,0 +1,656 @@
+//! Offscreen rendering without a window, for tests and CI machines without a GPU.
+//!
+//! Uses EGL (Mesa's surfaceless platform if available) with an OpenGL ES 3 context and
+//! renders into a framebuffer object. `libEGL.so.1` is loaded at runtime, so building with
+//! the `headless` feature doesn't require EGL to be installed. The GL tests of this crate
+//! fail without it, set `OGT_SKIP_GL=1` to skip them.
+
+use super::miniquad::{gl_string, GL_DEPTH24_STENCIL8, GL_DEPTH_STENCIL_ATTACHMENT};
+use crate::math::*;
+use crate::{
+    Application, Backend, Context, GlBackend, InputEvent, OgtError, RecordedFrame, Recording,
+    SoftwareBackend,
+};
+use image::RgbaImage;
+use miniquad::gl::*;
+use std::ffi::{c_void, CString};
+use std::os::raw::{c_char, c_int, c_uint};
+use std::rc::Rc;
+
+type EGLDisplay = *mut c_void;
+type EGLConfig = *mut c_void;
+type EGLContext = *mut c_void;
+type EGLSurface = *mut c_void;
+type EGLint = i32;
+type EGLBoolean = c_uint;
+
+const EGL_NONE: EGLint = 0x3038;
+const EGL_SURFACE_TYPE: EGLint = 0x3033;
+const EGL_RENDERABLE_TYPE: EGLint = 0x3040;
+const EGL_OPENGL_ES3_BIT: EGLint = 0x40;
+const EGL_OPENGL_ES_API: c_uint = 0x30A0;
+const EGL_CONTEXT_MAJOR_VERSION: EGLint = 0x3098;
+const EGL_PLATFORM_SURFACELESS_MESA: c_uint = 0x31DD;
+
+// Missing from miniquad's GL bindings
+const GL_PACK_ALIGNMENT: GLenum = 0x0D05;
+const GL_RENDERER: GLenum = 0x1F01;
+
+const RTLD_NOW: c_int = 2;
+
+extern "C" {
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dlclose(handle: *mut c_void) -> c_int;
+}
+
+struct Egl {
+    library: *mut c_void,
+    get_proc_address: extern "C" fn(*const c_char) -> *mut c_void,
+    make_current: extern "C" fn(EGLDisplay, EGLSurface, EGLSurface, EGLContext) -> EGLBoolean,
+    destroy_context: extern "C" fn(EGLDisplay, EGLContext) -> EGLBoolean,
+    terminate: extern "C" fn(EGLDisplay) -> EGLBoolean,
+    display: EGLDisplay,
+    context: EGLContext,
+}
+
+unsafe fn symbol<T>(library: *mut c_void, name: &str) -> Result<T, String> {
+    let c_name = CString::new(name).unwrap();
+    let ptr = dlsym(library, c_name.as_ptr());
+    if ptr.is_null() {
+        return Err(format!("{name} not found in libEGL"));
+    }
+    Ok(std::mem::transmute_copy(&ptr))
+}
+
+impl Egl {
+    fn new() -> Result<Self, String> {
+        unsafe {
+            let library = ["libEGL.so.1", "libEGL.so"]
+                .iter()
+                .map(|name| CString::new(*name).unwrap())
+                .map(|name| dlopen(name.as_ptr(), RTLD_NOW))
+                .find(|library| !library.is_null())
+                .ok_or("Could not load libEGL")?;
+            let mut egl = match Self::load(library) {
+                Ok(egl) => egl,
+                Err(err) => {
+                    dlclose(library);
+                    return Err(err);
+                }
+            };
+            egl.init()?;
+            Ok(egl)
+        }
+    }
+
+    unsafe fn load(library: *mut c_void) -> Result<Self, String> {
+        Ok(Self {
+            library,
+            get_proc_address: symbol(library, "eglGetProcAddress")?,
+            make_current: symbol(library, "eglMakeCurrent")?,
+            destroy_context: symbol(library, "eglDestroyContext")?,
+            terminate: symbol(library, "eglTerminate")?,
+            display: std::ptr::null_mut(),
+            context: std::ptr::null_mut(),
+        })
+    }
+
+    unsafe fn init(&mut self) -> Result<(), String> {
+        let get_display: extern "C" fn(*mut c_void) -> EGLDisplay =
+            symbol(self.library, "eglGetDisplay")?;
+        let initialize: extern "C" fn(EGLDisplay, *mut EGLint, *mut EGLint) -> EGLBoolean =
+            symbol(self.library, "eglInitialize")?;
+        let bind_api: extern "C" fn(c_uint) -> EGLBoolean = symbol(self.library, "eglBindAPI")?;
+        let choose_config: extern "C" fn(
+            EGLDisplay,
+            *const EGLint,
+            *mut EGLConfig,
+            EGLint,
+            *mut EGLint,
+        ) -> EGLBoolean = symbol(self.library, "eglChooseConfig")?;
+        let create_context: extern "C" fn(
+            EGLDisplay,
+            EGLConfig,
+            EGLContext,
+            *const EGLint,
+        ) -> EGLContext = symbol(self.library, "eglCreateContext")?;
+
+        // The surfaceless platform needs neither X11 nor Wayland, prefer it if available.
+        let get_platform_display = (self.get_proc_address)(c"eglGetPlatformDisplayEXT".as_ptr());
+        if !get_platform_display.is_null() {
+            let get_platform_display: extern "C" fn(
+                c_uint,
+                *mut c_void,
+                *const EGLint,
+            ) -> EGLDisplay = std::mem::transmute(get_platform_display);
+            let display = get_platform_display(
+                EGL_PLATFORM_SURFACELESS_MESA,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+            );
+            if !display.is_null()
+                && initialize(display, std::ptr::null_mut(), std::ptr::null_mut()) != 0
+            {
+                self.display = display;
+            }
+        }
+        if self.display.is_null() {
+            let display = get_display(std::ptr::null_mut());
+            if display.is_null()
+                || initialize(display, std::ptr::null_mut(), std::ptr::null_mut()) == 0
+            {
+                return Err("Could not initialize an EGL display".to_string());
+            }
+            self.display = display;
+        }
+
+        if bind_api(EGL_OPENGL_ES_API) == 0 {
+            return Err("EGL does not support OpenGL ES".to_string());
+        }
+        // Rendering goes into a framebuffer object, so any surface type will do.
+        let config_attributes = [
+            EGL_RENDERABLE_TYPE,
+            EGL_OPENGL_ES3_BIT,
+            EGL_SURFACE_TYPE,
+            0,
+            EGL_NONE,
+        ];
+        let mut config = std::ptr::null_mut();
+        let mut config_count = 0;
+        if choose_config(
+            self.display,
+            config_attributes.as_ptr(),
+            &mut config,
+            1,
+            &mut config_count,
+        ) == 0
+            || config_count == 0
+        {
+            return Err("No EGL config supporting OpenGL ES 3".to_string());
+        }
+        let context_attributes = [EGL_CONTEXT_MAJOR_VERSION, 3, EGL_NONE];
+        self.context = create_context(
+            self.display,
+            config,
+            std::ptr::null_mut(),
+            context_attributes.as_ptr(),
+        );
+        if self.context.is_null() {
+            return Err("Could not create an OpenGL ES 3 context".to_string());
+        }
+        if (self.make_current)(
+            self.display,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            self.context,
+        ) == 0
+        {
+            return Err("EGL does not support surfaceless contexts".to_string());
+        }
+        Ok(())
+    }
+
+    fn load_gl(&self) {
+        load_gl_funcs(|name| {
+            let name = CString::new(name).unwrap();
+            let ptr = (self.get_proc_address)(name.as_ptr());
+            unsafe { std::mem::transmute(ptr) }
+        });
+    }
+}
+
+impl Drop for Egl {
+    fn drop(&mut self) {
+        if !self.display.is_null() {
+            (self.make_current)(
+                self.display,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+            if !self.context.is_null() {
+                (self.destroy_context)(self.display, self.context);
+            }
+            (self.terminate)(self.display);
+        }
+        unsafe {
+            dlclose(self.library);
+        }
+    }
+}
+
+/// Runs an [`Application`] without a window, rendering into an offscreen image.
+///
+/// ```no_run
+/// # use ogt::*;
+/// # struct Game;
+/// # impl Application for Game {}
+/// let mut headless = Headless::new(320, 240).unwrap();
+/// let mut game = Game;
+/// headless.run_frames(&mut game, 10, 1.0 / 60.0);
+/// headless.read_pixels().save("frame.png").unwrap();
+/// ```
+///
+/// Only one GL instance should be alive per thread, GL resources belong to the current one.
+pub struct Headless {
+    context: Context,
+    surface: Surface,
+    quit: bool,
+}
+
+enum Surface {
+    Gl(GlSurface),
+    Software(Rc<SoftwareBackend>),
+}
+
+struct GlSurface {
+    framebuffer: GLuint,
+    renderbuffers: [GLuint; 2],
+    // Dropped last, releases the GL context
+    _egl: Egl,
+}
+
+impl Drop for GlSurface {
+    fn drop(&mut self) {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+            glDeleteFramebuffers(1, &self.framebuffer);
+            glDeleteRenderbuffers(2, self.renderbuffers.as_ptr());
+        }
+    }
+}
+
+impl Headless {
+    /// Creates a GL offscreen surface of `width` x `height` pixels.
+    pub fn new(width: u32, height: u32) -> Result<Self, OgtError> {
+        let egl = Egl::new().map_err(OgtError::Backend)?;
+        egl.load_gl();
+        let mut framebuffer = 0;
+        let mut renderbuffers = [0; 2];
+        unsafe {
+            glGenFramebuffers(1, &mut framebuffer);
+            glBindFramebuffer(GL_FRAMEBUFFER, framebuffer);
+            glGenRenderbuffers(2, renderbuffers.as_mut_ptr());
+            glBindRenderbuffer(GL_RENDERBUFFER, renderbuffers[0]);
+            glRenderbufferStorage(GL_RENDERBUFFER, GL_RGBA8, width as i32, height as i32);
+            glFramebufferRenderbuffer(
+                GL_FRAMEBUFFER,
+                GL_COLOR_ATTACHMENT0,
+                GL_RENDERBUFFER,
+                renderbuffers[0],
+            );
+            glBindRenderbuffer(GL_RENDERBUFFER, renderbuffers[1]);
+            glRenderbufferStorage(
+                GL_RENDERBUFFER,
+                GL_DEPTH24_STENCIL8,
+                width as i32,
+                height as i32,
+            );
+            glFramebufferRenderbuffer(
+                GL_FRAMEBUFFER,
+                GL_DEPTH_STENCIL_ATTACHMENT,
+                GL_RENDERBUFFER,
+                renderbuffers[1],
+            );
+            if glCheckFramebufferStatus(GL_FRAMEBUFFER) != GL_FRAMEBUFFER_COMPLETE {
+                return Err(OgtError::Backend(
+                    "Offscreen framebuffer is incomplete".to_string(),
+                ));
+            }
+            glViewport(0, 0, width as i32, height as i32);
+        }
+
+        let surface = Surface::Gl(GlSurface {
+            framebuffer,
+            renderbuffers,
+            _egl: egl,
+        });
+        Ok(Self::with_surface(
+            Rc::new(GlBackend::new()),
+            surface,
+            uvec2(width, height),
+        ))
+    }
+
+    /// Renders with the [`SoftwareBackend`] instead, which works everywhere and is pixel exact
+    /// across machines.
+    pub fn software(width: u32, height: u32) -> Self {
+        let backend = Rc::new(SoftwareBackend::new(width, height));
+        Self::with_surface(
+            backend.clone(),
+            Surface::Software(backend),
+            uvec2(width, height),
+        )
+    }
+
+    fn with_surface(backend: Rc<dyn Backend>, surface: Surface, size: UVec2) -> Self {
+        let mut context = Context::with_backend(backend);
+        context.headless = true;
+        context.screen_size = size;
+        Self {
+            context,
+            surface,
+            quit: false,
+        }
+    }
+
+    /// Use this to create the application, like the creator passed to [`crate::go`].
+    pub fn context(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.context.screen_size
+    }
+
+    /// Runs `frames` frames of `delta` seconds each without any input.
+    /// Stops early once the application calls [`Context::quit`] and
+    /// [`Application::quit_requested`] agrees.
+    pub fn run_frames<A: Application>(&mut self, app: &mut A, frames: usize, delta: f32) {
+        for _ in 0..frames {
+            self.run_frame(app, delta, Vec::new());
+        }
+    }
+
+    /// Runs one frame with the given input events, which are applied before updating.
+    pub fn run_frame<A: Application>(&mut self, app: &mut A, delta: f32, events: Vec<InputEvent>) {
+        self.replay_frame(
+            app,
+            RecordedFrame {
+                delta,
+                screen_size: self.size(),
+                events,
+                gamepads: Vec::new(),
+            },
+        );
+    }
+
+    /// Runs every frame of `script`, for example one captured with
+    /// [`Context::start_recording`]. The screen size is fixed to the offscreen surface.
+    pub fn run_script<A: Application>(&mut self, app: &mut A, script: &Recording) {
+        self.context.restore_input(&script.initial);
+        for frame in script.frames() {
+            self.replay_frame(
+                app,
+                RecordedFrame {
+                    screen_size: self.size(),
+                    ..frame.clone()
+                },
+            );
+        }
+    }
+
+    fn replay_frame<A: Application>(&mut self, app: &mut A, frame: RecordedFrame) {
+        if self.quit {
+            return;
+        }
+        self.context.replay_frame(app, &frame);
+        if self.context.quit_requested.take() {
+            self.quit = app.quit_requested(&self.context);
+        }
+        if let Surface::Gl(_) = self.surface {
+            unsafe {
+                glFinish();
+            }
+        }
+    }
+
+    /// Whether the application called [`Context::quit`] without vetoing it in
+    /// [`Application::quit_requested`].
+    pub fn quit_requested(&self) -> bool {
+        self.quit
+    }
+
+    /// Current content of the offscreen surface, top row first.
+    pub fn read_pixels(&self) -> RgbaImage {
+        let framebuffer = match &self.surface {
+            Surface::Gl(surface) => surface.framebuffer,
+            Surface::Software(backend) => return backend.image().clone(),
+        };
+        let size = self.size();
+        let mut pixels = vec![0u8; size.x as usize * size.y as usize * 4];
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, framebuffer);
+            glPixelStorei(GL_PACK_ALIGNMENT, 1);
+            glReadPixels(
+                0,
+                0,
+                size.x as i32,
+                size.y as i32,
+                GL_RGBA,
+                GL_UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut c_void,
+            );
+        }
+        // GL rows start at the bottom
+        let row = size.x as usize * 4;
+        let flipped = pixels.chunks_exact(row).rev().flatten().copied().collect();
+        RgbaImage::from_raw(size.x, size.y, flipped).unwrap()
+    }
+
+    /// Renderer and version string of the GL context, useful in CI logs.
+    pub fn renderer(&self) -> String {
+        if let Surface::Software(_) = self.surface {
+            return "software".to_string();
+        }
+        [GL_RENDERER, GL_VERSION].map(gl_string).join(" ")
+    }
+}
+
+#[cfg(test)]
+impl Headless {
+    /// A GL surface for tests. Without EGL this panics, unless `OGT_SKIP_GL` is set to skip
+    /// the test instead.
+    pub(crate) fn for_gl_test(width: u32, height: u32) -> Option<Self> {
+        match Self::new(width, height) {
+            Ok(headless) => Some(headless),
+            Err(err) if std::env::var_os("OGT_SKIP_GL").is_some_and(|value| value != "0") => {
+                eprintln!("Skipping GL test: {err}");
+                None
+            }
+            Err(err) => panic!("No GL for headless tests: {err}, set OGT_SKIP_GL=1 to skip them"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Color, ShapeBatch, SpriteBatch, Texture, TextureBuilder, BLUE, RED};
+
+    struct Scene {
+        shapes: ShapeBatch,
+        sprites: SpriteBatch,
+        texture: Texture,
+        frames: u32,
+        clicks: u32,
+    }
+
+    impl Application for Scene {
+        fn update(&mut self, context: &Context, _delta: f32) {
+            if context.is_mouse_button_pressed(crate::MouseButton::Left) {
+                self.clicks += 1;
+            }
+        }
+
+        fn render(&mut self, context: &Context, _delta: f32) {
+            self.frames += 1;
+            let size = context.screen_size().as_vec2();
+            let projection = Mat4::orthographic_rh_gl(0.0, size.x, size.y, 0.0, -1.0, 1.0);
+            context.clear_screen(BLUE);
+            self.shapes.set_model_view_projection_matrix(projection);
+            self.shapes
+                .add_filled_rect(context, vec2(0.0, 0.0), vec2(32.0, 32.0), RED);
+            self.shapes.draw(context);
+            self.sprites.set_model_view_projection_matrix(projection);
+            self.sprites.add(
+                context,
+                &self.texture,
+                self.texture.as_region(),
+                Color::rgb(255, 255, 255),
+                Vec2::ZERO,
+                vec2(48.0, 0.0),
+            );
+            self.sprites.draw(context);
+            if self.frames == 3 {
+                context.quit();
+            }
+        }
+    }
+
+    fn run_scene(mut headless: Headless) {
+        let context = headless.context();
+        let texture =
+            TextureBuilder::from_bytes(&[0, 255, 0, 255].repeat(16 * 16), 16, 16).build(context);
+        let mut scene = Scene {
+            shapes: ShapeBatch::new(context),
+            sprites: SpriteBatch::new(context),
+            texture,
+            frames: 0,
+            clicks: 0,
+        };
+
+        headless.run_frame(
+            &mut scene,
+            1.0 / 60.0,
+            vec![InputEvent::MouseDown(
+                crate::MouseButton::Left,
+                vec2(1.0, 1.0),
+            )],
+        );
+        headless.run_frames(&mut scene, 10, 1.0 / 60.0);
+        assert_eq!(scene.frames, 3);
+        assert_eq!(scene.clicks, 1);
+        assert!(headless.quit_requested());
+
+        let image = headless.read_pixels();
+        assert_eq!(image.dimensions(), (64, 32));
+        assert_eq!(image.get_pixel(4, 4).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(52, 4).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(40, 28).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn renders_offscreen() {
+        if let Some(headless) = Headless::for_gl_test(64, 32) {
+            run_scene(headless);
+        }
+    }
+
+    #[test]
+    fn renders_in_software() {
+        run_scene(Headless::software(64, 32));
+    }
+
+    #[test]
+    fn quit_can_be_vetoed() {
+        struct Stubborn {
+            frames: u32,
+            requests: u32,
+        }
+
+        impl Application for Stubborn {
+            fn render(&mut self, context: &Context, _delta: f32) {
+                self.frames += 1;
+                context.quit();
+            }
+
+            fn quit_requested(&mut self, _context: &Context) -> bool {
+                self.requests += 1;
+                self.requests == 3
+            }
+        }
+
+        let mut headless = Headless::software(4, 4);
+        let mut app = Stubborn {
+            frames: 0,
+            requests: 0,
+        };
+        headless.run_frames(&mut app, 2, 1.0 / 60.0);
+        assert_eq!(app.requests, 2);
+        assert!(!headless.quit_requested());
+        headless.run_frames(&mut app, 5, 1.0 / 60.0);
+        assert_eq!((app.frames, app.requests), (3, 3));
+        assert!(headless.quit_requested());
+    }
+
+    #[test]
+    fn large_batches_on_gl() {
+        let Some(mut headless) = Headless::for_gl_test(4, 2) else {
+            return;
+        };
+        let context = headless.context();
+        assert!(context.backend().supports_index32());
+        context.clear_screen(BLUE);
+        let texture = TextureBuilder::from_bytes(&RED.0, 1, 1).build(context);
+        let mut sprites = SpriteBatch::with_max_sprites(context, 20_000);
+        sprites.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+            0.0, 1.0, 0.0, 1.0, -1.0, 1.0,
+        ));
+        let region = texture.as_region();
+        for _ in 0..19_999 {
+            sprites.add(context, &texture, region, RED, Vec2::ZERO, vec2(-2.0, 0.0));
+        }
+        sprites.add(context, &texture, region, RED, Vec2::ZERO, Vec2::ZERO);
+        sprites.draw(context);
+        assert_eq!(headless.read_pixels().get_pixel(3, 1).0, RED.0);
+    }
+
+    #[test]
+    fn clear_resets_depth_after_translucent_sprites() {
+        const GL_DEPTH_WRITEMASK: u32 = 0x0B72;
+        let Some(mut headless) = Headless::for_gl_test(4, 2) else {
+            return;
+        };
+        let context = headless.context();
+        let texture = TextureBuilder::from_bytes(&[255; 4], 1, 1).build(context);
+        let region = texture.as_region();
+        let projection = Mat4::orthographic_rh_gl(0.0, 1.0, 0.0, 1.0, -1.0, 1.0);
+        let mut sprites = SpriteBatch::new(context);
+        sprites.set_model_view_projection_matrix(projection);
+        sprites.set_depth_test(context, true);
+        let mut shapes = ShapeBatch::new(context);
+        shapes.set_model_view_projection_matrix(projection);
+
+        // Opaque in front, then translucent with a read only depth buffer, then no depth test
+        context.clear_screen(BLUE);
+        sprites.add(
+            context,
+            &texture,
+            region,
+            RED,
+            Vec2::ZERO,
+            vec3(0.0, 0.0, 0.5),
+        );
+        let translucent = Color::rgba(255, 255, 255, 128);
+        sprites.add(
+            context,
+            &texture,
+            region,
+            translucent,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        sprites.draw(context);
+        shapes.add_filled_rect(context, Vec2::ZERO, Vec2::ONE, RED);
+        shapes.draw(context);
+
+        context.clear_screen(BLUE);
+        let mut mask = 0;
+        unsafe { ::miniquad::gl::glGetIntegerv(GL_DEPTH_WRITEMASK, &mut mask) };
+        assert_eq!(mask, 1);
+        // Behind the sprite of the previous frame
+        let green = Color::rgb(0, 255, 0);
+        sprites.add(
+            context,
+            &texture,
+            region,
+            green,
+            Vec2::ZERO,
+            vec3(0.0, 0.0, -0.5),
+        );
+        sprites.draw(context);
+        assert_eq!(headless.read_pixels().get_pixel(0, 0).0, green.0);
+    }
+}
@@ -1 +1,589 @@
-pub mod shape_batch;
+use super::*;
+use ::miniquad::gl::*;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+// Missing from miniquad's GL bindings
+pub(super) const GL_DEPTH24_STENCIL8: GLenum = 0x88F0;
+pub(super) const GL_DEPTH_STENCIL_ATTACHMENT: GLenum = 0x821A;
+const GL_MAX_TEXTURE_IMAGE_UNITS: GLenum = 0x8872;
+
+/// `glGetString`, empty if the context doesn't know `name`.
+pub(super) fn gl_string(name: GLenum) -> String {
+    unsafe {
+        let string = glGetString(name);
+        if string.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(string as *const c_char)
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+}
+
+/// Whether `version` is GLES 2 or WebGL 1, where e.g. 32 bit indices need an extension.
+/// An unknown version is treated the same.
+fn is_gles2(version: &str) -> bool {
+    version.is_empty() || version.starts_with("OpenGL ES 2") || version.starts_with("WebGL 1")
+}
+
+struct Program {
+    layout: VertexLayout,
+    /// Location of each layout attribute, `None` if unused by the shader
+    locations: Vec<Option<GLuint>>,
+    instance_locations: Vec<Option<GLuint>>,
+    uniforms: HashMap<String, GLint>,
+}
+
+/// OpenGL (ES) 3 backend, requires a current GL context with loaded function pointers.
+pub struct GlBackend {
+    vertex_array: GLuint,
+    /// Bit mask of enabled attribute locations
+    enabled_attributes: Cell<u32>,
+    /// Bit mask of attribute locations advancing per instance
+    instanced_attributes: Cell<u32>,
+    programs: RefCell<HashMap<ShaderId, Program>>,
+    buffers: RefCell<HashMap<BufferId, (BufferKind, BufferUsage)>>,
+    /// Depth and stencil renderbuffer of each render target, if requested
+    render_targets: RefCell<HashMap<RenderTargetId, Option<GLuint>>>,
+    /// Framebuffer bound at creation, the window or an offscreen surface
+    screen_framebuffer: GLuint,
+    bound_framebuffer: Cell<GLuint>,
+    /// Last applied blend mode, `None` before the first draw
+    blend: Cell<Option<BlendMode>>,
+    depth: Cell<Option<DepthMode>>,
+    /// Last applied depth mask, tracked apart from `depth` as disabling the test keeps it
+    depth_write: Cell<Option<bool>>,
+    max_texture_units: usize,
+    index32: bool,
+    instancing: bool,
+}
+
+impl GlBackend {
+    pub fn new() -> Self {
+        let mut vertex_array = 0;
+        let mut screen_framebuffer = 0;
+        let mut max_texture_units = 0;
+        unsafe {
+            glGenVertexArrays(1, &mut vertex_array);
+            glGetIntegerv(GL_FRAMEBUFFER_BINDING, &mut screen_framebuffer);
+            glGetIntegerv(GL_MAX_TEXTURE_IMAGE_UNITS, &mut max_texture_units);
+        }
+        let version = gl_string(GL_VERSION);
+        Self {
+            vertex_array,
+            enabled_attributes: Cell::new(0),
+            instanced_attributes: Cell::new(0),
+            programs: RefCell::default(),
+            buffers: RefCell::default(),
+            render_targets: RefCell::default(),
+            screen_framebuffer: screen_framebuffer as GLuint,
+            bound_framebuffer: Cell::new(screen_framebuffer as GLuint),
+            blend: Cell::new(None),
+            depth: Cell::new(None),
+            depth_write: Cell::new(None),
+            max_texture_units: max_texture_units.max(1) as usize,
+            index32: !is_gles2(&version)
+                || gl_string(GL_EXTENSIONS).contains("OES_element_index_uint"),
+            instancing: !is_gles2(&version),
+        }
+    }
+
+    fn apply_blend(&self, mode: BlendMode) {
+        if self.blend.get() == Some(mode) {
+            return;
+        }
+        self.blend.set(Some(mode));
+        let factor = |factor| match factor {
+            BlendFactor::One => GL_ONE,
+            BlendFactor::SourceAlpha => GL_SRC_ALPHA,
+            BlendFactor::OneMinusSourceAlpha => GL_ONE_MINUS_SRC_ALPHA,
+            BlendFactor::OneMinusSourceColor => GL_ONE_MINUS_SRC_COLOR,
+            BlendFactor::DestinationColor => GL_DST_COLOR,
+        };
+        unsafe {
+            match mode.factors() {
+                Some((source, destination)) => {
+                    glEnable(GL_BLEND);
+                    glBlendFunc(factor(source), factor(destination));
+                }
+                None => glDisable(GL_BLEND),
+            }
+        }
+    }
+
+    fn apply_depth(&self, mode: DepthMode) {
+        if self.depth.get() == Some(mode) {
+            return;
+        }
+        self.depth.set(Some(mode));
+        unsafe {
+            match mode {
+                DepthMode::Disabled => glDisable(GL_DEPTH_TEST),
+                DepthMode::TestAndWrite | DepthMode::Test => {
+                    glEnable(GL_DEPTH_TEST);
+                    glDepthFunc(GL_LEQUAL);
+                }
+            }
+        }
+        if mode != DepthMode::Disabled {
+            self.apply_depth_write(mode == DepthMode::TestAndWrite);
+        }
+    }
+
+    fn apply_depth_write(&self, write: bool) {
+        if self.depth_write.get() == Some(write) {
+            return;
+        }
+        self.depth_write.set(Some(write));
+        unsafe {
+            glDepthMask(write as GLboolean);
+        }
+    }
+}
+
+impl Default for GlBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for GlBackend {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteVertexArrays(1, &self.vertex_array);
+        }
+    }
+}
+
+unsafe fn compile_shader(shader_id: GLuint, shader: &str, shader_type: &str) -> Result<(), String> {
+    let len = shader.len();
+    glShaderSource(shader_id, 1, &(shader.as_ptr() as *const i8), &(len as i32));
+    glCompileShader(shader_id);
+
+    let mut result: GLint = 0;
+    glGetShaderiv(shader_id, GL_COMPILE_STATUS, &mut result);
+    let mut info_log_length: GLint = 0;
+    glGetShaderiv(shader_id, GL_INFO_LOG_LENGTH, &mut info_log_length);
+    if result == 0 && info_log_length > 0 {
+        let mut shader_error_message: Vec<u8> = Vec::with_capacity(info_log_length as usize);
+        glGetShaderInfoLog(
+            shader_id,
+            info_log_length,
+            std::ptr::null_mut(),
+            shader_error_message.as_mut_ptr() as *mut i8,
+        );
+        shader_error_message.set_len(info_log_length as usize - 1);
+        return Err(format!(
+            "Error in {}-shader: {}",
+            shader_type,
+            String::from_utf8_lossy(&shader_error_message)
+        ));
+    }
+    Ok(())
+}
+
+impl Backend for GlBackend {
+    fn create_texture(
+        &self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        filter: TextureFilter,
+    ) -> TextureId {
+        let filter = match filter {
+            TextureFilter::Linear => GL_LINEAR,
+            TextureFilter::Nearest => GL_NEAREST,
+        } as i32;
+        unsafe {
+            let mut texture_id = 0;
+            glGenTextures(1, &mut texture_id);
+            glBindTexture(GL_TEXTURE_2D, texture_id);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, filter);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, filter);
+
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_RGBA as i32,
+                width as i32,
+                height as i32,
+                0,
+                GL_RGBA,
+                GL_UNSIGNED_BYTE,
+                if data.is_empty() {
+                    std::ptr::null()
+                } else {
+                    data.as_ptr() as *const GLvoid
+                },
+            );
+            if width.is_power_of_two() && height.is_power_of_two() {
+                glGenerateMipmap(GL_TEXTURE_2D);
+            } else {
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as i32);
+            }
+            TextureId(texture_id)
+        }
+    }
+
+    fn delete_texture(&self, texture: TextureId) {
+        unsafe {
+            glDeleteTextures(1, &texture.0);
+        }
+    }
+
+    fn create_buffer(&self, kind: BufferKind, usage: BufferUsage) -> BufferId {
+        let mut buffer_id = 0;
+        unsafe {
+            glGenBuffers(1, &mut buffer_id);
+        }
+        self.buffers
+            .borrow_mut()
+            .insert(BufferId(buffer_id), (kind, usage));
+        BufferId(buffer_id)
+    }
+
+    fn update_buffer(&self, buffer: BufferId, data: &[u8]) {
+        let (target, usage) = match self.buffers.borrow().get(&buffer) {
+            Some(&(kind, usage)) => (
+                match kind {
+                    BufferKind::Vertex => GL_ARRAY_BUFFER,
+                    BufferKind::Index | BufferKind::Index32 => GL_ELEMENT_ARRAY_BUFFER,
+                },
+                match usage {
+                    BufferUsage::Static => GL_STATIC_DRAW,
+                    BufferUsage::Stream => GL_STREAM_DRAW,
+                },
+            ),
+            None => (GL_ARRAY_BUFFER, GL_STREAM_DRAW),
+        };
+        unsafe {
+            // WebGL fixes the target of a buffer on its first bind, so index data has to go
+            // through GL_ELEMENT_ARRAY_BUFFER. This changes the binding of the vertex array,
+            // but draw binds the index buffer again anyway.
+            glBindBuffer(target, buffer.0);
+            // According to https://thothonegan.tumblr.com/post/135193767243/glbuffersubdata-vs-glbufferdata
+            // this is better as the GPU can work on the buffer without blocking
+            glBufferData(
+                target,
+                data.len() as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+                usage,
+            );
+        }
+    }
+
+    fn delete_buffer(&self, buffer: BufferId) {
+        self.buffers.borrow_mut().remove(&buffer);
+        unsafe {
+            glDeleteBuffers(1, &buffer.0);
+        }
+    }
+
+    fn create_shader(
+        &self,
+        vertex_shader: &str,
+        fragment_shader: &str,
+        layout: &VertexLayout,
+    ) -> Result<ShaderId, OgtError> {
+        unsafe {
+            let vertex_shader_id = glCreateShader(GL_VERTEX_SHADER);
+            let fragment_shader_id = glCreateShader(GL_FRAGMENT_SHADER);
+
+            let compiled = compile_shader(vertex_shader_id, vertex_shader, "VERTEX")
+                .and_then(|_| compile_shader(fragment_shader_id, fragment_shader, "FRAGMENT"));
+            if let Err(err) = compiled {
+                glDeleteShader(vertex_shader_id);
+                glDeleteShader(fragment_shader_id);
+                return Err(OgtError::Shader(err));
+            }
+
+            let program_id = glCreateProgram();
+            glAttachShader(program_id, vertex_shader_id);
+            glAttachShader(program_id, fragment_shader_id);
+            glLinkProgram(program_id);
+            // Flagged for deletion, they go away with the program
+            glDeleteShader(vertex_shader_id);
+            glDeleteShader(fragment_shader_id);
+
+            let mut result: GLint = 0;
+            glGetProgramiv(program_id, GL_LINK_STATUS, &mut result);
+            let mut info_log_length = 0;
+            glGetProgramiv(program_id, GL_INFO_LOG_LENGTH, &mut info_log_length);
+            if result == 0 && info_log_length > 0 {
+                let mut program_error_message: Vec<u8> =
+                    Vec::with_capacity(info_log_length as usize);
+                glGetProgramInfoLog(
+                    program_id,
+                    info_log_length,
+                    std::ptr::null_mut(),
+                    program_error_message.as_mut_ptr() as *mut i8,
+                );
+                program_error_message.set_len(info_log_length as usize);
+                glDeleteProgram(program_id);
+                return Err(OgtError::Shader(
+                    String::from_utf8_lossy(&program_error_message).into_owned(),
+                ));
+            }
+
+            let locations = |attributes: &[VertexAttribute]| {
+                attributes
+                    .iter()
+                    .map(|attribute| {
+                        let name = CString::new(attribute.name).unwrap();
+                        let location = glGetAttribLocation(program_id, name.as_ptr());
+                        (location >= 0).then_some(location as GLuint)
+                    })
+                    .collect()
+            };
+            self.programs.borrow_mut().insert(
+                ShaderId(program_id),
+                Program {
+                    layout: layout.clone(),
+                    locations: locations(&layout.attributes),
+                    instance_locations: locations(&layout.instance_attributes),
+                    uniforms: HashMap::new(),
+                },
+            );
+            Ok(ShaderId(program_id))
+        }
+    }
+
+    fn delete_shader(&self, shader: ShaderId) {
+        self.programs.borrow_mut().remove(&shader);
+        unsafe {
+            glDeleteProgram(shader.0);
+        }
+    }
+
+    fn create_render_target(
+        &self,
+        texture: TextureId,
+        width: u32,
+        height: u32,
+        depth_stencil: bool,
+    ) -> Result<RenderTargetId, OgtError> {
+        unsafe {
+            let mut framebuffer = 0;
+            glGenFramebuffers(1, &mut framebuffer);
+            glBindFramebuffer(GL_FRAMEBUFFER, framebuffer);
+            glFramebufferTexture2D(
+                GL_FRAMEBUFFER,
+                GL_COLOR_ATTACHMENT0,
+                GL_TEXTURE_2D,
+                texture.0,
+                0,
+            );
+            let renderbuffer = depth_stencil.then(|| {
+                let mut renderbuffer = 0;
+                glGenRenderbuffers(1, &mut renderbuffer);
+                glBindRenderbuffer(GL_RENDERBUFFER, renderbuffer);
+                glRenderbufferStorage(
+                    GL_RENDERBUFFER,
+                    GL_DEPTH24_STENCIL8,
+                    width as i32,
+                    height as i32,
+                );
+                glFramebufferRenderbuffer(
+                    GL_FRAMEBUFFER,
+                    GL_DEPTH_STENCIL_ATTACHMENT,
+                    GL_RENDERBUFFER,
+                    renderbuffer,
+                );
+                renderbuffer
+            });
+            let status = glCheckFramebufferStatus(GL_FRAMEBUFFER);
+            glBindFramebuffer(GL_FRAMEBUFFER, self.bound_framebuffer.get());
+            if status != GL_FRAMEBUFFER_COMPLETE {
+                glDeleteFramebuffers(1, &framebuffer);
+                if let Some(renderbuffer) = renderbuffer {
+                    glDeleteRenderbuffers(1, &renderbuffer);
+                }
+                return Err(OgtError::Backend(format!(
+                    "Render target framebuffer is incomplete (status {status:#x})"
+                )));
+            }
+            let id = RenderTargetId(framebuffer);
+            self.render_targets.borrow_mut().insert(id, renderbuffer);
+            Ok(id)
+        }
+    }
+
+    fn delete_render_target(&self, target: RenderTargetId) {
+        if self.bound_framebuffer.get() == target.0 {
+            self.bind_render_target(None);
+        }
+        let renderbuffer = self.render_targets.borrow_mut().remove(&target).flatten();
+        unsafe {
+            glDeleteFramebuffers(1, &target.0);
+            if let Some(renderbuffer) = renderbuffer {
+                glDeleteRenderbuffers(1, &renderbuffer);
+            }
+        }
+    }
+
+    fn bind_render_target(&self, target: Option<RenderTargetId>) {
+        let framebuffer = target.map_or(self.screen_framebuffer, |target| target.0);
+        self.bound_framebuffer.set(framebuffer);
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, framebuffer);
+        }
+    }
+
+    fn clear(&self, color: Color) {
+        // A read only depth buffer is not cleared
+        self.apply_depth_write(true);
+        unsafe {
+            let c: [f32; 4] = color.0.map(|c| c as f32 / 255.0);
+            glClearColor(c[0], c[1], c[2], c[3]);
+            glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+        }
+    }
+
+    fn set_viewport(&self, position: IVec2, size: IVec2) {
+        unsafe {
+            glViewport(position.x, position.y, size.x, size.y);
+        }
+    }
+
+    fn max_texture_units(&self) -> usize {
+        self.max_texture_units
+    }
+
+    fn supports_index32(&self) -> bool {
+        self.index32
+    }
+
+    fn supports_instancing(&self) -> bool {
+        self.instancing
+    }
+
+    fn draw(&self, call: &DrawCall) {
+        if call.count == 0 {
+            return;
+        }
+        let mut programs = self.programs.borrow_mut();
+        let program = programs
+            .get_mut(&call.shader)
+            .expect("Shader was not created by this backend");
+        self.apply_blend(call.blend);
+        self.apply_depth(call.depth);
+        unsafe {
+            glUseProgram(call.shader.0);
+            for (name, uniform) in call.uniforms {
+                let location = *program.uniforms.entry(name.to_string()).or_insert_with(|| {
+                    let name = CString::new(*name).unwrap();
+                    glGetUniformLocation(call.shader.0, name.as_ptr())
+                });
+                match uniform {
+                    Uniform::Int(value) => glUniform1i(location, *value),
+                    Uniform::Float(value) => glUniform1f(location, *value),
+                    Uniform::Vec2(value) => glUniform2fv(location, 1, value.as_ref().as_ptr()),
+                    Uniform::Vec3(value) => glUniform3fv(location, 1, value.as_ref().as_ptr()),
+                    Uniform::Vec4(value) => glUniform4fv(location, 1, value.as_ref().as_ptr()),
+                    Uniform::Mat4(value) => glUniformMatrix4fv(
+                        location,
+                        1,
+                        GL_FALSE as u8,
+                        value.to_cols_array().as_ptr(),
+                    ),
+                }
+            }
+            for (unit, texture) in call.textures.iter().enumerate() {
+                glActiveTexture(GL_TEXTURE0 + unit as GLenum);
+                glBindTexture(GL_TEXTURE_2D, texture.0);
+            }
+            glActiveTexture(GL_TEXTURE0);
+
+            glBindVertexArray(self.vertex_array);
+            let layout = &program.layout;
+            let mut enabled = 0;
+            let mut instanced = 0;
+            let mut buffers = vec![(
+                call.vertices,
+                layout.stride,
+                &program.locations,
+                &layout.attributes,
+            )];
+            if let Some((instances, _)) = call.instances {
+                buffers.push((
+                    instances,
+                    layout.instance_stride,
+                    &program.instance_locations,
+                    &layout.instance_attributes,
+                ));
+            }
+            for (i, (buffer, stride, locations, attributes)) in buffers.into_iter().enumerate() {
+                glBindBuffer(GL_ARRAY_BUFFER, buffer.0);
+                for (location, attribute) in locations.iter().zip(attributes.iter()) {
+                    let Some(location) = *location else {
+                        continue;
+                    };
+                    enabled |= 1 << location;
+                    if i > 0 {
+                        instanced |= 1 << location;
+                    }
+                    let (data_type, normalized) = match attribute.format {
+                        VertexFormat::UByte4Norm => (GL_UNSIGNED_BYTE, GL_TRUE),
+                        _ => (GL_FLOAT, GL_FALSE),
+                    };
+                    glEnableVertexAttribArray(location);
+                    glVertexAttribPointer(
+                        location,
+                        attribute.format.components() as GLint,
+                        data_type,
+                        normalized as u8,
+                        stride as GLsizei,
+                        attribute.offset as *const GLvoid,
+                    );
+                }
+            }
+            let stale = self.enabled_attributes.get() & !enabled;
+            for location in (0..32).filter(|location| stale & (1 << location) != 0) {
+                glDisableVertexAttribArray(location);
+            }
+            self.enabled_attributes.set(enabled);
+            // Only touched with instancing, glVertexAttribDivisor is missing otherwise
+            let changed = self.instanced_attributes.get() ^ instanced;
+            for location in (0..32).filter(|location| changed & (1 << location) != 0) {
+                glVertexAttribDivisor(location, (instanced >> location) & 1);
+            }
+            self.instanced_attributes.set(instanced);
+
+            let instances = call.instances.map_or(1, |(_, count)| count) as GLsizei;
+            match call.indices {
+                Some(indices) => {
+                    let index_type = match self.buffers.borrow().get(&indices) {
+                        Some((BufferKind::Index32, _)) => GL_UNSIGNED_INT,
+                        _ => GL_UNSIGNED_SHORT,
+                    };
+                    glBindBuffer(GL_ELEMENT_ARRAY_BUFFER, indices.0);
+                    if call.instances.is_some() {
+                        glDrawElementsInstanced(
+                            GL_TRIANGLES,
+                            call.count as GLsizei,
+                            index_type,
+                            std::ptr::null(),
+                            instances,
+                        );
+                    } else {
+                        glDrawElements(
+                            GL_TRIANGLES,
+                            call.count as GLsizei,
+                            index_type,
+                            std::ptr::null(),
+                        );
+                    }
+                }
+                None if call.instances.is_some() => {
+                    glDrawArraysInstanced(GL_TRIANGLES, 0, call.count as GLsizei, instances);
+                }
+                None => glDrawArrays(GL_TRIANGLES, 0, call.count as GLsizei),
+            }
+        }
+    }
+}
@@ -1,55 +1,675 @@
-use crate::Color;
-use glam::IVec2;
+use crate::{Color, OgtError};
+use glam::*;
+use std::rc::Rc;
 
 #[cfg(feature = "miniquad")]
-pub mod miniquad;
+mod miniquad;
+#[cfg(feature = "miniquad")]
+pub use self::miniquad::GlBackend;
+
+mod software;
+pub use software::SoftwareBackend;
+
+#[cfg(feature = "headless")]
+mod headless;
+#[cfg(feature = "headless")]
+pub use headless::*;
 
 pub(crate) fn quit() {
     #[cfg(feature = "miniquad")]
     ::miniquad::window::request_quit();
 }
 
-pub(crate) fn configure_blend() {
+pub(crate) fn cancel_quit() {
     #[cfg(feature = "miniquad")]
-    unsafe {
-        use ::miniquad::gl::*;
-
-        glEnable(GL_BLEND);
-        glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
-    }
+    ::miniquad::window::cancel_quit();
 }
 
-pub(crate) fn clear_screen(color: Color) {
+pub(crate) fn show_mouse(shown: bool) {
     #[cfg(feature = "miniquad")]
-    unsafe {
-        use ::miniquad::gl::*;
-
-        glClearColor(0.0, 0.0, 0.4, 0.0);
-        let c: [f32; 4] = color.0.map(|c| c as f32 / 255.0).into();
-        glClearColor(c[0], c[1], c[2], c[3]);
-        glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
-    }
+    ::miniquad::window::show_mouse(shown);
 }
 
-pub(crate) fn set_viewport(top_left: IVec2, bottom_right: IVec2) {
+pub(crate) fn set_cursor_grab(grab: bool) {
     #[cfg(feature = "miniquad")]
-    unsafe {
-        use ::miniquad::gl::*;
+    ::miniquad::window::set_cursor_grab(grab);
+}
 
-        glViewport(top_left.x, top_left.y, bottom_right.x, bottom_right.y);
-    }
+pub(crate) fn set_mouse_cursor(icon: crate::CursorIcon) {
+    #[cfg(feature = "miniquad")]
+    ::miniquad::window::set_mouse_cursor(icon);
 }
 
-pub(crate) fn load_file(file: &str, handler: impl FnOnce(Result<Vec<u8>, ()>) + 'static) {
+pub(crate) fn load_file(file: &str, handler: impl FnOnce(Result<Vec<u8>, OgtError>) + 'static) {
     #[cfg(feature = "miniquad")]
     {
         let delegate = std::cell::RefCell::new(Some(handler));
+        let name = file.to_string();
         ::miniquad::fs::load_file(file, move |result| {
             let handler = delegate.take().unwrap();
             match result {
                 Ok(content) => handler(Ok(content)),
-                Err(err) => handler(Err(())),
+                Err(::miniquad::fs::Error::IOError(err)) => handler(Err(err.into())),
+                Err(err) => handler(Err(std::io::Error::other(format!("{name}: {err}")).into())),
             }
         });
     }
 }
+
+/// Handle of a texture owned by a [`Backend`], see [`crate::Texture`] for the managed version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShaderId(pub u32);
+
+/// Handle of a framebuffer, see [`crate::RenderTarget`] for the managed version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderTargetId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferKind {
+    Vertex,
+    /// `u16` indices into the vertex buffer
+    Index,
+    /// `u32` indices, only if [`Backend::supports_index32`]
+    Index32,
+}
+
+/// Hint how often a buffer is updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferUsage {
+    Static,
+    Stream,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureFilter {
+    #[default]
+    Linear,
+    Nearest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexFormat {
+    Float1,
+    Float2,
+    Float3,
+    Float4,
+    /// 4 bytes mapped to 0.0 - 1.0, used for colors
+    UByte4Norm,
+}
+
+impl VertexFormat {
+    pub fn components(self) -> usize {
+        match self {
+            VertexFormat::Float1 => 1,
+            VertexFormat::Float2 => 2,
+            VertexFormat::Float3 => 3,
+            VertexFormat::Float4 | VertexFormat::UByte4Norm => 4,
+        }
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            VertexFormat::UByte4Norm => 4,
+            format => format.components() * 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    /// Name of the attribute in the vertex shader
+    pub name: &'static str,
+    pub format: VertexFormat,
+    /// Byte offset within a vertex
+    pub offset: usize,
+}
+
+/// Memory layout of one vertex. Attributes are matched to the vertex shader by name.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VertexLayout {
+    pub stride: usize,
+    pub attributes: Vec<VertexAttribute>,
+    /// Layout of the [`DrawCall::instances`] buffer, empty unless drawing instanced.
+    pub instance_stride: usize,
+    pub instance_attributes: Vec<VertexAttribute>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Uniform {
+    Int(i32),
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Mat4(Mat4),
+}
+
+impl From<i32> for Uniform {
+    fn from(value: i32) -> Self {
+        Uniform::Int(value)
+    }
+}
+
+impl From<f32> for Uniform {
+    fn from(value: f32) -> Self {
+        Uniform::Float(value)
+    }
+}
+
+impl From<Vec2> for Uniform {
+    fn from(value: Vec2) -> Self {
+        Uniform::Vec2(value)
+    }
+}
+
+impl From<Vec3> for Uniform {
+    fn from(value: Vec3) -> Self {
+        Uniform::Vec3(value)
+    }
+}
+
+impl From<Vec4> for Uniform {
+    fn from(value: Vec4) -> Self {
+        Uniform::Vec4(value)
+    }
+}
+
+impl From<Mat4> for Uniform {
+    fn from(value: Mat4) -> Self {
+        Uniform::Mat4(value)
+    }
+}
+
+/// Normalized to 0 - 1, as a `vec4`
+impl From<Color> for Uniform {
+    fn from(color: Color) -> Self {
+        Uniform::Vec4(Vec4::from_array(color.0.map(|c| c as f32)) / 255.0)
+    }
+}
+
+/// How drawn colors are combined with what is already there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Regular transparency for straight (not premultiplied) alpha
+    #[default]
+    Alpha,
+    /// Transparency for colors already multiplied with their alpha
+    PremultipliedAlpha,
+    /// Adds the color weighted by its alpha, for glows and particles
+    Additive,
+    /// Multiplies with the destination, for light maps and shadows. Expects colors
+    /// premultiplied with their alpha, fully transparent texels keep the destination.
+    Multiply,
+    /// Inverse of multiply, brightens
+    Screen,
+    /// Overwrites the destination, alpha included
+    Replace,
+}
+
+/// Whether and how triangles are tested against the depth buffer. Window depth grows away
+/// from the viewer, triangles pass if they are as close or closer than what is there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DepthMode {
+    #[default]
+    Disabled,
+    /// Tests and writes depth, for opaque geometry
+    TestAndWrite,
+    /// Tests without writing, for translucent geometry drawn back to front
+    Test,
+}
+
+/// Factor of the source or destination color, like the arguments of `glBlendFunc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlendFactor {
+    One,
+    SourceAlpha,
+    OneMinusSourceAlpha,
+    OneMinusSourceColor,
+    DestinationColor,
+}
+
+impl BlendMode {
+    /// Source and destination factor, `None` if blending is off.
+    pub(crate) fn factors(self) -> Option<(BlendFactor, BlendFactor)> {
+        use BlendFactor::*;
+        match self {
+            BlendMode::Alpha => Some((SourceAlpha, OneMinusSourceAlpha)),
+            BlendMode::PremultipliedAlpha => Some((One, OneMinusSourceAlpha)),
+            BlendMode::Additive => Some((SourceAlpha, One)),
+            BlendMode::Multiply => Some((DestinationColor, OneMinusSourceAlpha)),
+            BlendMode::Screen => Some((One, OneMinusSourceColor)),
+            BlendMode::Replace => None,
+        }
+    }
+}
+
+/// Draws triangles.
+#[derive(Debug, Clone, Copy)]
+pub struct DrawCall<'a> {
+    pub shader: ShaderId,
+    pub vertices: BufferId,
+    /// Draws `count` indices from this buffer if set, otherwise `count` vertices.
+    pub indices: Option<BufferId>,
+    pub count: usize,
+    /// Buffer with the instance attributes and the number of instances. Draws everything once
+    /// per instance, see [`Backend::supports_instancing`].
+    pub instances: Option<(BufferId, usize)>,
+    /// Bound to texture units in order.
+    pub textures: &'a [TextureId],
+    pub uniforms: &'a [(&'a str, Uniform)],
+    pub blend: BlendMode,
+    pub depth: DepthMode,
+}
+
+/// Everything rendering needs from the graphics API. [`crate::Context::backend`] returns the
+/// one in use, which is OpenGL unless [`crate::Context::with_backend`] was used.
+pub trait Backend {
+    /// `data` is tightly packed RGBA, top row first. Empty for a texture with undefined
+    /// content, e.g. for a render target.
+    fn create_texture(
+        &self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        filter: TextureFilter,
+    ) -> TextureId;
+    fn delete_texture(&self, texture: TextureId);
+
+    fn create_buffer(&self, kind: BufferKind, usage: BufferUsage) -> BufferId;
+    /// Replaces the whole content of the buffer.
+    fn update_buffer(&self, buffer: BufferId, data: &[u8]);
+    fn delete_buffer(&self, buffer: BufferId);
+
+    fn create_shader(
+        &self,
+        vertex_shader: &str,
+        fragment_shader: &str,
+        layout: &VertexLayout,
+    ) -> Result<ShaderId, OgtError>;
+    fn delete_shader(&self, shader: ShaderId);
+
+    /// Framebuffer drawing into `texture` of `width` x `height` pixels. Like every GL
+    /// framebuffer, the bottom row ends up first in the texture.
+    fn create_render_target(
+        &self,
+        texture: TextureId,
+        width: u32,
+        height: u32,
+        depth_stencil: bool,
+    ) -> Result<RenderTargetId, OgtError>;
+    fn delete_render_target(&self, target: RenderTargetId);
+    /// Directs clears and draws to `target`, or back to the screen for `None`.
+    fn bind_render_target(&self, target: Option<RenderTargetId>);
+
+    /// Clears the color to `color` and depth to the far plane.
+    fn clear(&self, color: Color);
+    fn set_viewport(&self, position: IVec2, size: IVec2);
+    /// How many textures a [`DrawCall`] can bind.
+    fn max_texture_units(&self) -> usize;
+    /// Whether [`BufferKind::Index32`] buffers can be drawn, which GLES 2 and WebGL 1 need an
+    /// extension for.
+    fn supports_index32(&self) -> bool;
+    /// Whether [`DrawCall::instances`] can be used, which needs GL(ES) 3.
+    fn supports_instancing(&self) -> bool;
+    fn draw(&self, call: &DrawCall);
+}
+
+/// Used by [`crate::Context::default`], creates handles but doesn't render anything.
+#[derive(Default)]
+pub(crate) struct NullBackend {
+    next_id: std::cell::Cell<u32>,
+}
+
+impl NullBackend {
+    fn next(&self) -> u32 {
+        let id = self.next_id.get() + 1;
+        self.next_id.set(id);
+        id
+    }
+}
+
+impl Backend for NullBackend {
+    fn create_texture(&self, _: u32, _: u32, _: &[u8], _: TextureFilter) -> TextureId {
+        TextureId(self.next())
+    }
+
+    fn delete_texture(&self, _: TextureId) {}
+
+    fn create_buffer(&self, _: BufferKind, _: BufferUsage) -> BufferId {
+        BufferId(self.next())
+    }
+
+    fn update_buffer(&self, _: BufferId, _: &[u8]) {}
+
+    fn delete_buffer(&self, _: BufferId) {}
+
+    fn create_shader(&self, _: &str, _: &str, _: &VertexLayout) -> Result<ShaderId, OgtError> {
+        Ok(ShaderId(self.next()))
+    }
+
+    fn delete_shader(&self, _: ShaderId) {}
+
+    fn create_render_target(
+        &self,
+        _: TextureId,
+        _: u32,
+        _: u32,
+        _: bool,
+    ) -> Result<RenderTargetId, OgtError> {
+        Ok(RenderTargetId(self.next()))
+    }
+
+    fn delete_render_target(&self, _: RenderTargetId) {}
+
+    fn bind_render_target(&self, _: Option<RenderTargetId>) {}
+
+    fn clear(&self, _: Color) {}
+
+    fn set_viewport(&self, _: IVec2, _: IVec2) {}
+
+    fn max_texture_units(&self) -> usize {
+        // The minimum of GLES 2
+        8
+    }
+
+    fn supports_index32(&self) -> bool {
+        true
+    }
+
+    fn supports_instancing(&self) -> bool {
+        true
+    }
+
+    fn draw(&self, _: &DrawCall) {}
+}
+
+/// Backend of a [`crate::Context`], a [`NullBackend`] by default.
+#[derive(Clone)]
+pub(crate) struct SharedBackend(pub(crate) Rc<dyn Backend>);
+
+impl Default for SharedBackend {
+    fn default() -> Self {
+        Self(Rc::new(NullBackend::default()))
+    }
+}
+
+/// Raw bytes of vertex or index data for [`Backend::update_buffer`].
+pub(crate) fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    // SAFETY: only used with `#[repr(C)]` vertices without padding and integers
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Context, ShapeBatch, SortMode, SpriteBatch, TextureBuilder, WHITE};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Draw {
+        shader: ShaderId,
+        indexed: bool,
+        count: usize,
+        textures: Vec<TextureId>,
+        blend: BlendMode,
+    }
+
+    /// Logs draw calls and deleted resources
+    #[derive(Default)]
+    struct RecordingBackend {
+        null: NullBackend,
+        /// Like GLES 2 without the extension
+        no_index32: bool,
+        draws: RefCell<Vec<Draw>>,
+        deleted_textures: RefCell<Vec<TextureId>>,
+    }
+
+    impl Backend for RecordingBackend {
+        fn create_texture(&self, w: u32, h: u32, data: &[u8], filter: TextureFilter) -> TextureId {
+            self.null.create_texture(w, h, data, filter)
+        }
+
+        fn delete_texture(&self, texture: TextureId) {
+            self.deleted_textures.borrow_mut().push(texture);
+        }
+
+        fn create_buffer(&self, kind: BufferKind, usage: BufferUsage) -> BufferId {
+            self.null.create_buffer(kind, usage)
+        }
+
+        fn update_buffer(&self, _: BufferId, _: &[u8]) {}
+
+        fn delete_buffer(&self, _: BufferId) {}
+
+        fn create_shader(
+            &self,
+            vs: &str,
+            fs: &str,
+            layout: &VertexLayout,
+        ) -> Result<ShaderId, OgtError> {
+            self.null.create_shader(vs, fs, layout)
+        }
+
+        fn delete_shader(&self, _: ShaderId) {}
+
+        fn create_render_target(
+            &self,
+            texture: TextureId,
+            width: u32,
+            height: u32,
+            depth_stencil: bool,
+        ) -> Result<RenderTargetId, OgtError> {
+            self.null
+                .create_render_target(texture, width, height, depth_stencil)
+        }
+
+        fn delete_render_target(&self, _: RenderTargetId) {}
+
+        fn bind_render_target(&self, _: Option<RenderTargetId>) {}
+
+        fn clear(&self, _: Color) {}
+
+        fn set_viewport(&self, _: IVec2, _: IVec2) {}
+
+        fn max_texture_units(&self) -> usize {
+            self.null.max_texture_units()
+        }
+
+        fn supports_index32(&self) -> bool {
+            !self.no_index32
+        }
+
+        fn supports_instancing(&self) -> bool {
+            self.null.supports_instancing()
+        }
+
+        fn draw(&self, call: &DrawCall) {
+            self.draws.borrow_mut().push(Draw {
+                shader: call.shader,
+                indexed: call.indices.is_some(),
+                count: call.count,
+                textures: call.textures.to_vec(),
+                blend: call.blend,
+            });
+        }
+    }
+
+    #[test]
+    fn batches_render_through_backend() {
+        let backend = Rc::new(RecordingBackend::default());
+        let context = Context::with_backend(backend.clone());
+        let texture = TextureBuilder::from_bytes(&[255; 16], 2, 2).build(&context);
+        let texture_id = texture.id();
+
+        let mut sprites = SpriteBatch::new(&context);
+        sprites.add(
+            &context,
+            &texture,
+            texture.as_region(),
+            WHITE,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        sprites.add(
+            &context,
+            &texture,
+            texture.as_region(),
+            WHITE,
+            Vec2::ZERO,
+            Vec2::ONE,
+        );
+        sprites.draw(&context);
+        sprites.draw(&context);
+
+        let mut shapes = ShapeBatch::new(&context);
+        shapes.add_filled_rect(&context, Vec2::ZERO, Vec2::ONE, WHITE);
+        shapes.set_blend_mode(&context, BlendMode::Alpha);
+        shapes.set_blend_mode(&context, BlendMode::Additive);
+        shapes.add_filled_rect(&context, Vec2::ZERO, Vec2::ONE, WHITE);
+        shapes.draw(&context);
+
+        let draws = backend.draws.borrow().clone();
+        assert_eq!(draws.len(), 3);
+        assert_eq!(draws[1].blend, BlendMode::Alpha);
+        assert_eq!(draws[2].blend, BlendMode::Additive);
+        assert_eq!((draws[0].indexed, draws[0].count), (true, 12));
+        assert_eq!(draws[0].textures, vec![texture_id]);
+        assert_eq!((draws[1].indexed, draws[1].count), (false, 6));
+        assert_ne!(draws[0].shader, draws[1].shader);
+
+        // Pending sprites keep their texture alive
+        let region = texture.as_region();
+        sprites.add(&context, &texture, region, WHITE, Vec2::ZERO, Vec2::ZERO);
+        drop(texture);
+        assert!(backend.deleted_textures.borrow().is_empty());
+        sprites.draw(&context);
+        assert_eq!(*backend.deleted_textures.borrow(), vec![texture_id]);
+    }
+
+    #[test]
+    fn changing_shader_draws_pending_items() {
+        let backend = Rc::new(RecordingBackend::default());
+        let context = Context::with_backend(backend.clone());
+        let texture = TextureBuilder::from_bytes(&[255; 4], 1, 1).build(&context);
+        let region = texture.as_region();
+
+        let mut sprites = SpriteBatch::new(&context);
+        let sprite_shader = SpriteBatch::create_shader(&context, "").unwrap();
+        sprites.add(&context, &texture, region, WHITE, Vec2::ZERO, Vec2::ZERO);
+        sprites.set_shader(&context, Some(sprite_shader.clone()));
+        sprites.add(&context, &texture, region, WHITE, Vec2::ZERO, Vec2::ZERO);
+        sprites.set_shader(&context, Some(sprite_shader));
+        sprites.draw(&context);
+
+        let mut shapes = ShapeBatch::new(&context);
+        let shape_shader = ShapeBatch::create_shader(&context, "").unwrap();
+        shapes.add_filled_rect(&context, Vec2::ZERO, Vec2::ONE, WHITE);
+        shapes.set_shader(&context, Some(shape_shader));
+        shapes.add_filled_rect(&context, Vec2::ZERO, Vec2::ONE, WHITE);
+        shapes.set_shader(&context, None);
+        shapes.add_filled_rect(&context, Vec2::ZERO, Vec2::ONE, WHITE);
+        shapes.draw(&context);
+
+        let draws = backend.draws.borrow().clone();
+        let counts = draws.iter().map(|draw| draw.count).collect::<Vec<_>>();
+        assert_eq!(counts, vec![6, 6, 6, 6, 6]);
+        assert_ne!(draws[0].shader, draws[1].shader);
+        assert_ne!(draws[2].shader, draws[3].shader);
+        assert_eq!(draws[2].shader, draws[4].shader);
+    }
+
+    #[test]
+    fn sprite_batch_switches_textures() {
+        let backend = Rc::new(RecordingBackend::default());
+        let context = Context::with_backend(backend.clone());
+        let a = TextureBuilder::from_bytes(&[255; 16], 2, 2).build(&context);
+        let b = TextureBuilder::from_bytes(&[255; 16], 2, 2).build(&context);
+        let draw = |sprites: &mut SpriteBatch, mode| {
+            sprites.set_sort_mode(mode);
+            for (texture, z) in [(&a, 0.5), (&b, 0.0), (&a, -0.5), (&a, 0.25)] {
+                let region = texture.as_region();
+                sprites.add(
+                    &context,
+                    texture,
+                    region,
+                    WHITE,
+                    Vec2::ZERO,
+                    vec3(0.0, 0.0, z),
+                );
+            }
+            sprites.draw(&context);
+            let draws = backend.draws.take();
+            draws
+                .into_iter()
+                .map(|draw| (draw.textures, draw.count / 6))
+                .collect::<Vec<_>>()
+        };
+        let mut sprites = SpriteBatch::new(&context);
+        let (a, b) = (a.id(), b.id());
+        // Both textures fit into one draw call
+        assert_eq!(
+            draw(&mut sprites, SortMode::Submission),
+            vec![(vec![a, b], 4)]
+        );
+        assert_eq!(draw(&mut sprites, SortMode::Depth), vec![(vec![a, b], 4)]);
+
+        // Custom shaders only sample one texture
+        sprites.set_shader(
+            &context,
+            Some(SpriteBatch::create_shader(&context, "").unwrap()),
+        );
+        assert_eq!(
+            draw(&mut sprites, SortMode::Submission),
+            vec![(vec![a], 1), (vec![b], 1), (vec![a], 2)]
+        );
+        assert_eq!(
+            draw(&mut sprites, SortMode::Texture),
+            vec![(vec![a], 3), (vec![b], 1)]
+        );
+        // Identity projection, the largest z is the farthest
+        assert_eq!(
+            draw(&mut sprites, SortMode::Depth),
+            vec![(vec![a], 2), (vec![b], 1), (vec![a], 1)]
+        );
+    }
+
+    #[test]
+    fn sprite_batch_capacity_depends_on_index_support() {
+        let backend = Rc::new(RecordingBackend {
+            no_index32: true,
+            ..Default::default()
+        });
+        let context = Context::with_backend(backend.clone());
+        assert_eq!(
+            SpriteBatch::with_max_sprites(&context, 100).max_sprites(),
+            100
+        );
+        let mut sprites = SpriteBatch::with_max_sprites(&context, 100_000);
+        assert_eq!(sprites.max_sprites(), 16384);
+
+        let texture = TextureBuilder::from_bytes(&[255; 4], 1, 1).build(&context);
+        for _ in 0..16385 {
+            sprites.add(
+                &context,
+                &texture,
+                texture.as_region(),
+                WHITE,
+                Vec2::ZERO,
+                Vec2::ZERO,
+            );
+        }
+        sprites.draw(&context);
+        let counts: Vec<_> = backend.draws.take().iter().map(|draw| draw.count).collect();
+        assert_eq!(counts, vec![16384 * 6, 6]);
+
+        let context = Context::with_backend(Rc::new(RecordingBackend::default()));
+        let sprites = SpriteBatch::with_max_sprites(&context, 100_000);
+        assert_eq!(sprites.max_sprites(), 100_000);
+    }
+}
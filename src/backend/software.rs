@@ -0,0 +1,695 @@
+//! Pure Rust rasterizer, renders into an [`RgbaImage`] without any GPU.
+
+use super::*;
+use crate::Context;
+use image::RgbaImage;
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+
+struct SoftwareTexture {
+    image: RgbaImage,
+    filter: TextureFilter,
+    /// GL repeats power of two textures, the GL backend clamps the others
+    repeat: bool,
+}
+
+impl SoftwareTexture {
+    fn texel(&self, x: i32, y: i32) -> Vec4 {
+        let (width, height) = (self.image.width() as i32, self.image.height() as i32);
+        let (x, y) = if self.repeat {
+            (x.rem_euclid(width), y.rem_euclid(height))
+        } else {
+            (x.clamp(0, width - 1), y.clamp(0, height - 1))
+        };
+        Vec4::from_array(self.image.get_pixel(x as u32, y as u32).0.map(|c| c as f32)) / 255.0
+    }
+
+    fn sample(&self, uv: Vec2) -> Vec4 {
+        let size = vec2(self.image.width() as f32, self.image.height() as f32);
+        let position = uv * size;
+        match self.filter {
+            TextureFilter::Nearest => {
+                let texel = position.floor();
+                self.texel(texel.x as i32, texel.y as i32)
+            }
+            TextureFilter::Linear => {
+                let position = position - 0.5;
+                let base = position.floor();
+                let fraction = position - base;
+                let (x, y) = (base.x as i32, base.y as i32);
+                let top = self.texel(x, y).lerp(self.texel(x + 1, y), fraction.x);
+                let bottom = self
+                    .texel(x, y + 1)
+                    .lerp(self.texel(x + 1, y + 1), fraction.x);
+                top.lerp(bottom, fraction.y)
+            }
+        }
+    }
+}
+
+/// Which layout attributes carry what, see [`SoftwareBackend`].
+struct Attributes {
+    position: VertexAttribute,
+    uv: Option<VertexAttribute>,
+    color: Option<VertexAttribute>,
+    texture: Option<VertexAttribute>,
+}
+
+impl Attributes {
+    fn new(layout: &VertexLayout) -> Result<Self, String> {
+        let (position, rest) = layout
+            .attributes
+            .split_first()
+            .ok_or("Vertex layout without attributes")?;
+        if position.format == VertexFormat::UByte4Norm {
+            return Err("First vertex attribute must be the position".to_string());
+        }
+        Ok(Self {
+            position: *position,
+            uv: rest
+                .iter()
+                .find(|attribute| attribute.format == VertexFormat::Float2)
+                .copied(),
+            color: rest
+                .iter()
+                .find(|attribute| {
+                    matches!(
+                        attribute.format,
+                        VertexFormat::UByte4Norm | VertexFormat::Float4
+                    )
+                })
+                .copied(),
+            texture: rest
+                .iter()
+                .find(|attribute| attribute.format == VertexFormat::Float1)
+                .copied(),
+        })
+    }
+}
+
+fn read_attribute(data: &[u8], offset: usize, format: VertexFormat) -> Vec4 {
+    let mut value = [0.0, 0.0, 0.0, 1.0];
+    if format == VertexFormat::UByte4Norm {
+        for (i, byte) in data[offset..offset + 4].iter().enumerate() {
+            value[i] = *byte as f32 / 255.0;
+        }
+    } else {
+        for (i, bytes) in data[offset..offset + format.size()]
+            .chunks_exact(4)
+            .enumerate()
+        {
+            value[i] = f32::from_le_bytes(bytes.try_into().unwrap());
+        }
+    }
+    Vec4::from_array(value)
+}
+
+const SUBPIXELS: f32 = 256.0;
+
+#[derive(Clone, Copy)]
+struct ShadedVertex {
+    /// Window coordinates, origin at the bottom left like GL
+    position: Vec2,
+    depth: f32,
+    inverse_w: f32,
+    uv: Vec2,
+    color: Vec4,
+    /// Index into the textures of the draw call
+    texture: usize,
+}
+
+/// Edge function, positive if `p` is left of `a` -> `b`
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Top-left fill rule in image space for counter clockwise triangles, so shared edges are
+/// drawn once. Window coordinates point up, so that's the bottom edge here.
+fn is_top_left(a: Vec2, b: Vec2) -> bool {
+    (a.y == b.y && b.x > a.x) || b.y < a.y
+}
+
+struct SoftwareTarget {
+    texture: TextureId,
+    /// Empty without a depth buffer, as in [`State::depth`]
+    depth: Vec<f32>,
+}
+
+struct State {
+    /// What draws go to, top row first
+    target: RgbaImage,
+    /// Window depth of each pixel of `target`, in the same order. Empty if there is none.
+    depth: Vec<f32>,
+    /// The screen while a render target is bound
+    screen: RgbaImage,
+    screen_depth: Vec<f32>,
+    /// Its image and depth are moved to `target` and `depth` meanwhile
+    bound: Option<RenderTargetId>,
+    render_targets: HashMap<RenderTargetId, SoftwareTarget>,
+    viewport: (IVec2, IVec2),
+    next_id: u32,
+    textures: HashMap<TextureId, SoftwareTexture>,
+    buffers: HashMap<BufferId, (BufferKind, Vec<u8>)>,
+    shaders: HashMap<ShaderId, VertexLayout>,
+}
+
+impl State {
+    fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn bound_texture(&self) -> Option<TextureId> {
+        self.bound
+            .and_then(|bound| self.render_targets.get(&bound))
+            .map(|target| target.texture)
+    }
+
+    fn bind(&mut self, target: Option<RenderTargetId>) {
+        // Textures are stored like GL framebuffers, bottom row first
+        if let Some(bound) = self.bound.take() {
+            let screen = std::mem::take(&mut self.screen);
+            let mut image = std::mem::replace(&mut self.target, screen);
+            let depth = std::mem::replace(&mut self.depth, std::mem::take(&mut self.screen_depth));
+            image::imageops::flip_vertical_in_place(&mut image);
+            if let Some(target) = self.render_targets.get_mut(&bound) {
+                target.depth = depth;
+                if let Some(texture) = self.textures.get_mut(&target.texture) {
+                    texture.image = image;
+                }
+            }
+        }
+        let Some((id, target)) = target.and_then(|id| {
+            self.render_targets
+                .get_mut(&id)
+                .filter(|target| self.textures.contains_key(&target.texture))
+                .map(|target| (id, target))
+        }) else {
+            return;
+        };
+        let texture = self.textures.get_mut(&target.texture).unwrap();
+        let mut image = std::mem::take(&mut texture.image);
+        image::imageops::flip_vertical_in_place(&mut image);
+        self.screen = std::mem::replace(&mut self.target, image);
+        self.screen_depth = std::mem::replace(&mut self.depth, std::mem::take(&mut target.depth));
+        self.bound = Some(id);
+    }
+
+    fn blend(&mut self, x: u32, y: u32, color: Vec4, mode: BlendMode) {
+        let pixel = self.target.get_pixel_mut(x, y);
+        let color = match mode.factors() {
+            // Like glBlendFunc, alpha included
+            Some((source, destination)) => {
+                let target = Vec4::from_array(pixel.0.map(|c| c as f32)) / 255.0;
+                let factor = |factor| match factor {
+                    BlendFactor::One => Vec4::ONE,
+                    BlendFactor::SourceAlpha => Vec4::splat(color.w),
+                    BlendFactor::OneMinusSourceAlpha => Vec4::splat(1.0 - color.w),
+                    BlendFactor::OneMinusSourceColor => 1.0 - color,
+                    BlendFactor::DestinationColor => target,
+                };
+                color * factor(source) + target * factor(destination)
+            }
+            None => color,
+        };
+        pixel.0 = (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.0)
+            .round()
+            .to_array()
+            .map(|c| c as u8);
+    }
+
+    fn triangle(
+        &mut self,
+        mut vertices: [ShadedVertex; 3],
+        textures: &[TextureId],
+        blend: BlendMode,
+        depth_mode: DepthMode,
+    ) {
+        let mut area = edge(
+            vertices[0].position,
+            vertices[1].position,
+            vertices[2].position,
+        );
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+        if area < 0.0 {
+            vertices.swap(1, 2);
+            area = -area;
+        }
+        let [v0, v1, v2] = vertices;
+        let (p0, p1, p2) = (v0.position, v1.position, v2.position);
+
+        let (viewport_position, viewport_size) = self.viewport;
+        let height = self.target.height() as i32;
+        let min_x = viewport_position.x.max(0);
+        let min_y = viewport_position.y.max(0);
+        let max_x = (viewport_position.x + viewport_size.x).min(self.target.width() as i32);
+        let max_y = (viewport_position.y + viewport_size.y).min(height);
+        let lower = p0.min(p1).min(p2).floor();
+        let upper = p0.max(p1).max(p2).ceil();
+        let (start_x, end_x) = (min_x.max(lower.x as i32), max_x.min(upper.x as i32));
+        let (start_y, end_y) = (min_y.max(lower.y as i32), max_y.min(upper.y as i32));
+
+        let bound_texture = self.bound_texture();
+        // Like a flat varying, the first vertex decides
+        let texture = textures.get(vertices[0].texture).copied();
+        let include = [
+            is_top_left(p1, p2),
+            is_top_left(p2, p0),
+            is_top_left(p0, p1),
+        ];
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [edge(p1, p2, p), edge(p2, p0, p), edge(p0, p1, p)];
+                if weights
+                    .iter()
+                    .zip(include)
+                    .any(|(weight, include)| *weight < 0.0 || (*weight == 0.0 && !include))
+                {
+                    continue;
+                }
+                let [b0, b1, b2] = weights.map(|weight| weight / area);
+                let depth = b0 * v0.depth + b1 * v1.depth + b2 * v2.depth;
+                if !(-1.0..=1.0).contains(&depth) {
+                    continue;
+                }
+                let row = (height - 1 - y) as usize;
+                let index = row * self.target.width() as usize + x as usize;
+                // Like glDepthFunc(GL_LEQUAL) on window depth
+                let window_depth = (depth + 1.0) / 2.0;
+                if depth_mode != DepthMode::Disabled && index < self.depth.len() {
+                    if window_depth > self.depth[index] {
+                        continue;
+                    }
+                    if depth_mode == DepthMode::TestAndWrite {
+                        self.depth[index] = window_depth;
+                    }
+                }
+                // Perspective correct interpolation
+                let [w0, w1, w2] = [b0 * v0.inverse_w, b1 * v1.inverse_w, b2 * v2.inverse_w];
+                let w = w0 + w1 + w2;
+                let (w0, w1, w2) = (w0 / w, w1 / w, w2 / w);
+                let mut color = v0.color * w0 + v1.color * w1 + v2.color * w2;
+                // Sampling the bound render target is undefined in GL, here it's ignored
+                if let Some(texture) = texture
+                    .filter(|texture| bound_texture != Some(*texture))
+                    .and_then(|texture| self.textures.get(&texture))
+                {
+                    color *= texture.sample(v0.uv * w0 + v1.uv * w1 + v2.uv * w2);
+                }
+                self.blend(x as u32, (height - 1 - y) as u32, color, blend);
+            }
+        }
+    }
+}
+
+/// [`Backend`] rendering on the CPU, for thumbnails, server side rendering and pixel exact
+/// tests. Pass it to [`crate::Context::with_backend`] and read the result with
+/// [`SoftwareBackend::image`].
+///
+/// Shaders are not executed. Instead the vertex layout is interpreted: the first attribute is
+/// the position, transformed by the `viewProjectionMatrix` uniform. The first `UByte4Norm` or
+/// `Float4` attribute is the color and the first `Float2` attribute after the position holds
+/// texture coordinates. The texture is multiplied with the color, it's the first one of the
+/// call or the one picked by the first `Float1` attribute of each triangle. That covers
+/// [`crate::SpriteBatch`] and [`crate::ShapeBatch`], instanced draws are not supported.
+/// Coverage follows the usual GPU rules, filtered texture lookups can be off by a few color
+/// levels compared to GL.
+pub struct SoftwareBackend {
+    state: RefCell<State>,
+}
+
+impl SoftwareBackend {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            state: RefCell::new(State {
+                target: RgbaImage::new(width, height),
+                depth: vec![1.0; width as usize * height as usize],
+                screen: RgbaImage::default(),
+                screen_depth: Vec::new(),
+                bound: None,
+                render_targets: HashMap::new(),
+                viewport: (IVec2::ZERO, ivec2(width as i32, height as i32)),
+                next_id: 0,
+                textures: HashMap::new(),
+                buffers: HashMap::new(),
+                shaders: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        let image = self.image();
+        uvec2(image.width(), image.height())
+    }
+
+    /// The rendered image of the screen, top row first.
+    pub fn image(&self) -> Ref<'_, RgbaImage> {
+        Ref::map(self.state.borrow(), |state| match state.bound {
+            Some(_) => &state.screen,
+            None => &state.target,
+        })
+    }
+
+    /// Renders one image with a fresh [`crate::Context`] of the given size.
+    pub fn render(width: u32, height: u32, draw: impl FnOnce(&mut Context)) -> RgbaImage {
+        let backend = Rc::new(Self::new(width, height));
+        let mut context = Context::with_backend(backend.clone());
+        context.headless = true;
+        context.screen_size = uvec2(width, height);
+        draw(&mut context);
+        drop(context);
+        let image = backend.image().clone();
+        image
+    }
+}
+
+impl Backend for SoftwareBackend {
+    fn create_texture(
+        &self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        filter: TextureFilter,
+    ) -> TextureId {
+        let mut state = self.state.borrow_mut();
+        let id = TextureId(state.next_id());
+        let image = if data.is_empty() {
+            RgbaImage::new(width, height)
+        } else {
+            RgbaImage::from_raw(width, height, data.to_vec())
+                .expect("Texture data doesn't match its size")
+        };
+        state.textures.insert(
+            id,
+            SoftwareTexture {
+                image,
+                filter,
+                repeat: width.is_power_of_two() && height.is_power_of_two(),
+            },
+        );
+        id
+    }
+
+    fn delete_texture(&self, texture: TextureId) {
+        let mut state = self.state.borrow_mut();
+        if state.bound_texture() == Some(texture) {
+            state.bind(None);
+        }
+        state.textures.remove(&texture);
+    }
+
+    fn create_buffer(&self, kind: BufferKind, _usage: BufferUsage) -> BufferId {
+        let mut state = self.state.borrow_mut();
+        let id = BufferId(state.next_id());
+        state.buffers.insert(id, (kind, Vec::new()));
+        id
+    }
+
+    fn update_buffer(&self, buffer: BufferId, data: &[u8]) {
+        if let Some((_, content)) = self.state.borrow_mut().buffers.get_mut(&buffer) {
+            content.clear();
+            content.extend_from_slice(data);
+        }
+    }
+
+    fn delete_buffer(&self, buffer: BufferId) {
+        self.state.borrow_mut().buffers.remove(&buffer);
+    }
+
+    fn create_shader(
+        &self,
+        _vertex_shader: &str,
+        _fragment_shader: &str,
+        layout: &VertexLayout,
+    ) -> Result<ShaderId, OgtError> {
+        Attributes::new(layout).map_err(OgtError::Shader)?;
+        let mut state = self.state.borrow_mut();
+        let id = ShaderId(state.next_id());
+        state.shaders.insert(id, layout.clone());
+        Ok(id)
+    }
+
+    fn delete_shader(&self, shader: ShaderId) {
+        self.state.borrow_mut().shaders.remove(&shader);
+    }
+
+    fn create_render_target(
+        &self,
+        texture: TextureId,
+        width: u32,
+        height: u32,
+        depth_stencil: bool,
+    ) -> Result<RenderTargetId, OgtError> {
+        let mut state = self.state.borrow_mut();
+        if !state.textures.contains_key(&texture) {
+            return Err(OgtError::Backend(format!(
+                "Render target for unknown texture {texture:?}"
+            )));
+        }
+        let id = RenderTargetId(state.next_id());
+        let depth = match depth_stencil {
+            true => vec![1.0; width as usize * height as usize],
+            false => Vec::new(),
+        };
+        state
+            .render_targets
+            .insert(id, SoftwareTarget { texture, depth });
+        Ok(id)
+    }
+
+    fn delete_render_target(&self, target: RenderTargetId) {
+        let mut state = self.state.borrow_mut();
+        if state.bound == Some(target) {
+            state.bind(None);
+        }
+        state.render_targets.remove(&target);
+    }
+
+    fn bind_render_target(&self, target: Option<RenderTargetId>) {
+        self.state.borrow_mut().bind(target);
+    }
+
+    fn clear(&self, color: Color) {
+        let mut state = self.state.borrow_mut();
+        for pixel in state.target.pixels_mut() {
+            pixel.0 = color.0;
+        }
+        state.depth.fill(1.0);
+    }
+
+    fn set_viewport(&self, position: IVec2, size: IVec2) {
+        self.state.borrow_mut().viewport = (position, size);
+    }
+
+    fn max_texture_units(&self) -> usize {
+        16
+    }
+
+    fn supports_index32(&self) -> bool {
+        true
+    }
+
+    fn supports_instancing(&self) -> bool {
+        false
+    }
+
+    fn draw(&self, call: &DrawCall) {
+        if call.instances.is_some() {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        let layout = state
+            .shaders
+            .get(&call.shader)
+            .expect("Shader was not created by this backend")
+            .clone();
+        let attributes = Attributes::new(&layout).unwrap();
+        let matrix = call
+            .uniforms
+            .iter()
+            .find_map(|(name, uniform)| match uniform {
+                Uniform::Mat4(matrix) if *name == "viewProjectionMatrix" => Some(*matrix),
+                _ => None,
+            })
+            .unwrap_or(Mat4::IDENTITY);
+        let (viewport_position, viewport_size) = state.viewport;
+
+        let (_, vertices) = &state.buffers[&call.vertices];
+        let indices: Vec<usize> = match call.indices.map(|indices| &state.buffers[&indices]) {
+            Some((BufferKind::Index32, data)) => data
+                .chunks_exact(4)
+                .take(call.count)
+                .map(|index| u32::from_le_bytes(index.try_into().unwrap()) as usize)
+                .collect(),
+            Some((_, data)) => data
+                .chunks_exact(2)
+                .take(call.count)
+                .map(|index| u16::from_le_bytes([index[0], index[1]]) as usize)
+                .collect(),
+            None => (0..call.count).collect(),
+        };
+        let shaded: Vec<Option<ShadedVertex>> = indices
+            .iter()
+            .map(|index| {
+                let base = index * layout.stride;
+                if base + layout.stride > vertices.len() {
+                    return None;
+                }
+                let mut position = read_attribute(
+                    vertices,
+                    base + attributes.position.offset,
+                    attributes.position.format,
+                );
+                if attributes.position.format == VertexFormat::Float2 {
+                    position.z = 0.0;
+                }
+                let clip = matrix * position;
+                // No clipping against the near plane, drop what is behind the camera
+                if clip.w <= 0.0 {
+                    return None;
+                }
+                let ndc = clip.truncate() / clip.w;
+                let window = viewport_position.as_vec2()
+                    + (ndc.truncate() + 1.0) / 2.0 * viewport_size.as_vec2();
+                Some(ShadedVertex {
+                    // Snap to a subpixel grid like GPUs do, so edges exactly on pixel centers
+                    // don't depend on rounding errors of the projection
+                    position: (window * SUBPIXELS).round() / SUBPIXELS,
+                    depth: ndc.z,
+                    inverse_w: 1.0 / clip.w,
+                    uv: attributes.uv.map_or(Vec2::ZERO, |uv| {
+                        read_attribute(vertices, base + uv.offset, uv.format)
+                            .truncate()
+                            .truncate()
+                    }),
+                    color: attributes.color.map_or(Vec4::ONE, |color| {
+                        read_attribute(vertices, base + color.offset, color.format)
+                    }),
+                    texture: attributes.texture.map_or(0, |texture| {
+                        read_attribute(vertices, base + texture.offset, texture.format).x as usize
+                    }),
+                })
+            })
+            .collect();
+
+        for triangle in shaded.chunks_exact(3) {
+            if let [Some(v0), Some(v1), Some(v2)] = triangle {
+                state.triangle([*v0, *v1, *v2], call.textures, call.blend, call.depth);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ShapeBatch, SpriteBatch, TextureBuilder, BLACK, RED, WHITE};
+
+    fn setup(width: u32, height: u32) -> (Rc<SoftwareBackend>, Context, Mat4) {
+        let backend = Rc::new(SoftwareBackend::new(width, height));
+        let context = Context::with_backend(backend.clone());
+        let projection = Mat4::orthographic_rh_gl(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+        (backend, context, projection)
+    }
+
+    #[test]
+    fn shapes_cover_exact_pixels() {
+        let (backend, context, projection) = setup(8, 8);
+        context.clear_screen(BLACK);
+        let mut shapes = ShapeBatch::new(&context);
+        shapes.set_model_view_projection_matrix(projection);
+        shapes.add_filled_rect(&context, vec2(2.0, 1.0), vec2(5.0, 3.0), RED);
+        shapes.draw(&context);
+
+        let image = backend.image();
+        let covered: Vec<_> = image
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel.0 == RED.0)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        // Shared diagonal is drawn exactly once
+        assert_eq!(
+            covered,
+            vec![(2, 1), (3, 1), (4, 1), (2, 2), (3, 2), (4, 2)]
+        );
+        assert_eq!(image.get_pixel(0, 0).0, BLACK.0);
+    }
+
+    #[test]
+    fn multiply_respects_alpha() {
+        let (backend, context, projection) = setup(4, 1);
+        context.clear_screen(Color::rgb(200, 100, 50));
+        let mut shapes = ShapeBatch::new(&context);
+        shapes.set_model_view_projection_matrix(projection);
+        shapes.set_blend_mode(&context, BlendMode::Multiply);
+        shapes.add_filled_rect(
+            &context,
+            vec2(0.0, 0.0),
+            vec2(1.0, 1.0),
+            Color::rgba(0, 0, 0, 0),
+        );
+        // Half transparent black, premultiplied
+        let shadow = Color::rgba(0, 0, 0, 128);
+        shapes.add_filled_rect(&context, vec2(1.0, 0.0), vec2(2.0, 1.0), shadow);
+        shapes.add_filled_rect(&context, vec2(2.0, 0.0), vec2(3.0, 1.0), BLACK);
+        shapes.draw(&context);
+
+        let image = backend.image();
+        assert_eq!(image.get_pixel(0, 0).0, [200, 100, 50, 255]);
+        assert_eq!(image.get_pixel(1, 0).0[..3], [100, 50, 25]);
+        assert_eq!(image.get_pixel(2, 0).0[..3], [0, 0, 0]);
+    }
+
+    #[test]
+    fn sprites_are_textured_tinted_and_blended() {
+        let (backend, context, projection) = setup(4, 2);
+        context.clear_screen(WHITE);
+        // Left texel opaque blue, right half transparent red
+        let texture = TextureBuilder::from_bytes(&[0, 0, 255, 255, 255, 0, 0, 128], 2, 1)
+            .filter(TextureFilter::Nearest)
+            .build(&context);
+        let mut sprites = SpriteBatch::new(&context);
+        sprites
+            .set_model_view_projection_matrix(projection * Mat4::from_scale(vec3(2.0, 2.0, 1.0)));
+        let region = texture.as_region();
+        sprites.add(
+            &context,
+            &texture,
+            region,
+            Color::rgb(255, 255, 0),
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        sprites.draw(&context);
+
+        let image = backend.image();
+        // Blue tinted yellow gives black
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [0, 0, 0, 255]);
+        // Half transparent red blended over white
+        assert_eq!(image.get_pixel(3, 0).0, [255, 127, 127, 191]);
+    }
+
+    #[test]
+    fn sprites_beyond_u16_indices() {
+        let (backend, context, projection) = setup(4, 2);
+        context.clear_screen(WHITE);
+        let texture = TextureBuilder::from_bytes(&RED.0, 1, 1).build(&context);
+        let mut sprites = SpriteBatch::with_max_sprites(&context, 20_000);
+        assert_eq!(sprites.max_sprites(), 20_000);
+        sprites
+            .set_model_view_projection_matrix(projection * Mat4::from_scale(vec3(4.0, 2.0, 1.0)));
+        let region = texture.as_region();
+        for _ in 0..19_999 {
+            sprites.add(&context, &texture, region, RED, Vec2::ZERO, vec2(-2.0, 0.0));
+        }
+        // Only reachable with 32 bit indices
+        sprites.add(&context, &texture, region, RED, Vec2::ZERO, Vec2::ZERO);
+        sprites.draw(&context);
+        assert_eq!(backend.image().get_pixel(0, 0).0, RED.0);
+        assert_eq!(backend.image().get_pixel(3, 1).0, RED.0);
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// Everything that can go wrong in ogt. Cheap to clone, so failed assets can report it
+/// every time they are asked.
+#[derive(Debug, Clone)]
+pub enum OgtError {
+    /// Reading or writing a file failed.
+    Io(Arc<std::io::Error>),
+    /// An image could not be decoded or encoded.
+    Image(Arc<image::ImageError>),
+    /// A font could not be parsed or rasterized.
+    Font(String),
+    /// Compiling or linking a shader failed, contains the driver's log.
+    Shader(String),
+    /// Corrupt or malformed data, like a broken replay or action settings.
+    InvalidData(String),
+    /// The graphics backend could not be initialized.
+    Backend(String),
+}
+
+impl fmt::Display for OgtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OgtError::Io(err) => write!(f, "IO error: {err}"),
+            OgtError::Image(err) => write!(f, "Image error: {err}"),
+            OgtError::Font(err) => write!(f, "Font error: {err}"),
+            OgtError::Shader(err) => write!(f, "Shader error: {err}"),
+            OgtError::InvalidData(err) => write!(f, "Invalid data: {err}"),
+            OgtError::Backend(err) => write!(f, "Backend error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for OgtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OgtError::Io(err) => Some(err.as_ref()),
+            OgtError::Image(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for OgtError {
+    fn from(err: std::io::Error) -> Self {
+        OgtError::Io(Arc::new(err))
+    }
+}
+
+impl From<image::ImageError> for OgtError {
+    fn from(err: image::ImageError) -> Self {
+        OgtError::Image(Arc::new(err))
+    }
+}
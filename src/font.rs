@@ -1,7 +1,7 @@
 use crate::rect_pack::*;
 use crate::sprite_batch::*;
 use crate::texture::*;
-use crate::{Color, Context};
+use crate::{Color, Context, OgtError};
 use fontdue as fd;
 use glam::{vec2, Vec2};
 use image::{Rgba, RgbaImage};
@@ -27,12 +27,12 @@ impl Font {
         pos: Vec2,
         color: Color,
     ) {
-        batch.set_texture(self.texture.clone());
         let mut c_pos = pos;
         for c in txt.chars() {
             let glyph = &self.glyphs[c as usize - 32];
             batch.add(
                 context,
+                &self.texture,
                 glyph.sprite,
                 color,
                 Vec2::ZERO,
@@ -71,11 +71,24 @@ pub struct LoadedFont {
 
 impl LoadedFont {
     pub fn from_bytes(data: &[u8]) -> Self {
-        let font = Rc::new(fd::Font::from_bytes(data, fd::FontSettings::default()).unwrap());
-        Self { font }
+        Self::try_from_bytes(data).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, OgtError> {
+        let font = fd::Font::from_bytes(data, fd::FontSettings::default())
+            .map_err(|err| OgtError::Font(err.to_string()))?;
+        Ok(Self {
+            font: Rc::new(font),
+        })
     }
 
     pub fn create_font(&self, context: &Context, size: f32) -> Font {
+        self.try_create_font(context, size)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Fails if the glyphs of this size don't fit into one texture.
+    pub fn try_create_font(&self, context: &Context, size: f32) -> Result<Font, OgtError> {
         let rasterized_chars: Vec<_> = (32..255_u8)
             .map(|c| self.font.rasterize(c as char, size))
             .collect();
@@ -83,7 +96,9 @@ impl LoadedFont {
             .iter()
             .map(|c| Rect::wh(c.0.width as u32 + 2, c.0.height as u32 + 2))
             .collect();
-        let dim = pack(&mut rects, 4096).unwrap();
+        let dim = pack(&mut rects, 4096).ok_or_else(|| {
+            OgtError::Font(format!("Glyphs of size {size} don't fit into a texture"))
+        })?;
         let mut img = RgbaImage::new(dim.0, dim.1);
         for (c, r) in rasterized_chars.iter().zip(rects.iter()) {
             for (p, &v) in c.1.iter().enumerate() {
@@ -110,14 +125,13 @@ impl LoadedFont {
                 })
                 .collect(),
         );
-        Font { texture, glyphs }
+        Ok(Font { texture, glyphs })
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use glam::uvec2;
 
     #[test]
     fn test() {
@@ -0,0 +1,465 @@
+use crate::math::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    /// A on Xbox, Cross on PlayStation
+    South,
+    /// B on Xbox, Circle on PlayStation
+    East,
+    /// X on Xbox, Square on PlayStation
+    West,
+    /// Y on Xbox, Triangle on PlayStation
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Guide,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const COUNT: usize = 17;
+    pub const ALL: [GamepadButton; GamepadButton::COUNT] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::LeftTrigger,
+        GamepadButton::RightTrigger,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::Guide,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const COUNT: usize = 6;
+    pub const ALL: [GamepadAxis; GamepadAxis::COUNT] = [
+        GamepadAxis::LeftX,
+        GamepadAxis::LeftY,
+        GamepadAxis::RightX,
+        GamepadAxis::RightY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadSide {
+    Left,
+    Right,
+}
+
+/// Raw state of one connected pad, as reported by a [`GamepadSource`].
+/// Stick axes range from -1 to 1 (positive y is up), triggers from 0 to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadReport {
+    pub id: u32,
+    pub buttons: [bool; GamepadButton::COUNT],
+    pub axes: [f32; GamepadAxis::COUNT],
+}
+
+impl GamepadReport {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            buttons: [false; GamepadButton::COUNT],
+            axes: [0.0; GamepadAxis::COUNT],
+        }
+    }
+
+    pub fn button(mut self, button: GamepadButton, down: bool) -> Self {
+        self.buttons[button as usize] = down;
+        self
+    }
+
+    pub fn axis(mut self, axis: GamepadAxis, value: f32) -> Self {
+        self.axes[axis as usize] = value;
+        self
+    }
+}
+
+/// Provides controller state to the [`crate::Context`]. Install one with
+/// [`crate::Context::set_gamepad_source`]. With the `gilrs` feature [`crate::go`] installs a
+/// `GilrsSource`, without it no pads are reported unless you install a source of your own.
+pub trait GamepadSource {
+    /// Called once per frame. Push one report for every currently connected pad.
+    fn poll(&mut self, pads: &mut Vec<GamepadReport>);
+}
+
+/// Reads real controllers through [gilrs](https://docs.rs/gilrs).
+#[cfg(feature = "gilrs")]
+pub struct GilrsSource {
+    gilrs: gilrs::Gilrs,
+}
+
+#[cfg(feature = "gilrs")]
+impl GilrsSource {
+    pub fn new() -> Result<Self, crate::OgtError> {
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => Ok(Self { gilrs }),
+            Err(err) => Err(crate::OgtError::Backend(format!(
+                "Gamepads not available: {err}"
+            ))),
+        }
+    }
+}
+
+#[cfg(feature = "gilrs")]
+impl GamepadSource for GilrsSource {
+    fn poll(&mut self, pads: &mut Vec<GamepadReport>) {
+        use gilrs::{Axis, Button};
+        const BUTTONS: [(GamepadButton, Button); GamepadButton::COUNT] = [
+            (GamepadButton::South, Button::South),
+            (GamepadButton::East, Button::East),
+            (GamepadButton::West, Button::West),
+            (GamepadButton::North, Button::North),
+            (GamepadButton::LeftShoulder, Button::LeftTrigger),
+            (GamepadButton::RightShoulder, Button::RightTrigger),
+            (GamepadButton::LeftTrigger, Button::LeftTrigger2),
+            (GamepadButton::RightTrigger, Button::RightTrigger2),
+            (GamepadButton::Select, Button::Select),
+            (GamepadButton::Start, Button::Start),
+            (GamepadButton::Guide, Button::Mode),
+            (GamepadButton::LeftStick, Button::LeftThumb),
+            (GamepadButton::RightStick, Button::RightThumb),
+            (GamepadButton::DPadUp, Button::DPadUp),
+            (GamepadButton::DPadDown, Button::DPadDown),
+            (GamepadButton::DPadLeft, Button::DPadLeft),
+            (GamepadButton::DPadRight, Button::DPadRight),
+        ];
+        const STICKS: [(GamepadAxis, Axis); 4] = [
+            (GamepadAxis::LeftX, Axis::LeftStickX),
+            (GamepadAxis::LeftY, Axis::LeftStickY),
+            (GamepadAxis::RightX, Axis::RightStickX),
+            (GamepadAxis::RightY, Axis::RightStickY),
+        ];
+
+        // Events only update the cached state read below
+        while self.gilrs.next_event().is_some() {}
+        for (id, pad) in self.gilrs.gamepads() {
+            let mut report = GamepadReport::new(usize::from(id) as u32);
+            for (button, gilrs_button) in BUTTONS {
+                report = report.button(button, pad.is_pressed(gilrs_button));
+            }
+            for (axis, gilrs_axis) in STICKS {
+                report = report.axis(axis, pad.value(gilrs_axis));
+            }
+            // Analog triggers are buttons with a value in gilrs
+            let trigger = |button| pad.button_data(button).map_or(0.0, |data| data.value());
+            report = report
+                .axis(GamepadAxis::LeftTrigger, trigger(Button::LeftTrigger2))
+                .axis(GamepadAxis::RightTrigger, trigger(Button::RightTrigger2));
+            pads.push(report);
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct GamepadButtonState {
+    down: bool,
+    pressed: bool,
+    released: bool,
+}
+
+pub struct Gamepad {
+    id: u32,
+    buttons: [GamepadButtonState; GamepadButton::COUNT],
+    axes: [f32; GamepadAxis::COUNT],
+    stick_dead_zone: f32,
+    trigger_dead_zone: f32,
+}
+
+impl Gamepad {
+    fn new(id: u32) -> Self {
+        Self {
+            id,
+            buttons: [GamepadButtonState::default(); GamepadButton::COUNT],
+            axes: [0.0; GamepadAxis::COUNT],
+            stick_dead_zone: 0.0,
+            trigger_dead_zone: 0.0,
+        }
+    }
+
+    fn update(&mut self, report: &GamepadReport) {
+        for (state, &down) in self.buttons.iter_mut().zip(report.buttons.iter()) {
+            state.pressed = down && !state.down;
+            state.released = !down && state.down;
+            state.down = down;
+        }
+        self.axes = report.axes;
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn is_button_down(&self, button: GamepadButton) -> bool {
+        self.buttons[button as usize].down
+    }
+
+    pub fn is_button_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons[button as usize].pressed
+    }
+
+    pub fn is_button_released(&self, button: GamepadButton) -> bool {
+        self.buttons[button as usize].released
+    }
+
+    /// Axis value with dead zones applied, see [`Gamepad::stick`] and [`Gamepad::trigger`].
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        match axis {
+            GamepadAxis::LeftX => self.stick(GamepadSide::Left).x,
+            GamepadAxis::LeftY => self.stick(GamepadSide::Left).y,
+            GamepadAxis::RightX => self.stick(GamepadSide::Right).x,
+            GamepadAxis::RightY => self.stick(GamepadSide::Right).y,
+            GamepadAxis::LeftTrigger => self.trigger(GamepadSide::Left),
+            GamepadAxis::RightTrigger => self.trigger(GamepadSide::Right),
+        }
+    }
+
+    /// Unfiltered axis value, no dead zone applied.
+    pub fn raw_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    /// Stick position with a radial dead zone applied. The result is rescaled, so it
+    /// still covers the full range outside of the dead zone.
+    pub fn stick(&self, side: GamepadSide) -> Vec2 {
+        let (x, y) = match side {
+            GamepadSide::Left => (GamepadAxis::LeftX, GamepadAxis::LeftY),
+            GamepadSide::Right => (GamepadAxis::RightX, GamepadAxis::RightY),
+        };
+        let raw = vec2(self.raw_axis(x), self.raw_axis(y));
+        let length = raw.length();
+        if length <= self.stick_dead_zone {
+            return Vec2::ZERO;
+        }
+        let scaled = ((length - self.stick_dead_zone) / (1.0 - self.stick_dead_zone)).min(1.0);
+        raw / length * scaled
+    }
+
+    /// Trigger value in the range 0 to 1 with the dead zone applied.
+    pub fn trigger(&self, side: GamepadSide) -> f32 {
+        let axis = match side {
+            GamepadSide::Left => GamepadAxis::LeftTrigger,
+            GamepadSide::Right => GamepadAxis::RightTrigger,
+        };
+        let raw = self.raw_axis(axis).clamp(0.0, 1.0);
+        if raw <= self.trigger_dead_zone {
+            0.0
+        } else {
+            (raw - self.trigger_dead_zone) / (1.0 - self.trigger_dead_zone)
+        }
+    }
+}
+
+const MAX_DEAD_ZONE: f32 = 0.95;
+
+pub struct Gamepads {
+    source: Option<Box<dyn GamepadSource>>,
+    pads: Vec<Gamepad>,
+    reports: Vec<GamepadReport>,
+    stick_dead_zone: f32,
+    trigger_dead_zone: f32,
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self {
+            source: None,
+            pads: vec![],
+            reports: vec![],
+            stick_dead_zone: 0.2,
+            trigger_dead_zone: 0.05,
+        }
+    }
+}
+
+impl Gamepads {
+    pub(crate) fn set_source(&mut self, source: Box<dyn GamepadSource>) {
+        self.source = Some(source);
+    }
+
+    pub(crate) fn set_dead_zones(&mut self, stick: f32, trigger: f32) {
+        // A dead zone of 1 would leave nothing to rescale the remaining range into
+        let stick = stick.clamp(0.0, MAX_DEAD_ZONE);
+        let trigger = trigger.clamp(0.0, MAX_DEAD_ZONE);
+        self.stick_dead_zone = stick;
+        self.trigger_dead_zone = trigger;
+        for pad in self.pads.iter_mut() {
+            pad.stick_dead_zone = stick;
+            pad.trigger_dead_zone = trigger;
+        }
+    }
+
+    pub(crate) fn poll(&mut self) {
+        let Some(source) = self.source.as_mut() else {
+            return;
+        };
+        self.reports.clear();
+        source.poll(&mut self.reports);
+        self.update();
+    }
+
+    pub(crate) fn set_reports(&mut self, reports: &[GamepadReport]) {
+        self.reports.clear();
+        self.reports.extend_from_slice(reports);
+        self.update();
+    }
+
+    /// Raw reports of the last poll.
+    pub(crate) fn reports(&self) -> &[GamepadReport] {
+        &self.reports
+    }
+
+    fn update(&mut self) {
+        let reports = &self.reports;
+        self.pads
+            .retain(|pad| reports.iter().any(|report| report.id == pad.id));
+        for report in reports.iter() {
+            let pad = match self.pads.iter().position(|pad| pad.id == report.id) {
+                Some(index) => &mut self.pads[index],
+                None => {
+                    let mut pad = Gamepad::new(report.id);
+                    pad.stick_dead_zone = self.stick_dead_zone;
+                    pad.trigger_dead_zone = self.trigger_dead_zone;
+                    self.pads.push(pad);
+                    self.pads.last_mut().unwrap()
+                }
+            };
+            pad.update(report);
+        }
+    }
+
+    /// All connected pads, in the order they were connected.
+    pub fn pads(&self) -> &[Gamepad] {
+        &self.pads
+    }
+
+    pub fn pad(&self, id: u32) -> Option<&Gamepad> {
+        self.pads.iter().find(|pad| pad.id == id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct FakePads(Rc<RefCell<Vec<GamepadReport>>>);
+
+    impl GamepadSource for FakePads {
+        fn poll(&mut self, pads: &mut Vec<GamepadReport>) {
+            pads.extend(self.0.borrow().iter().cloned());
+        }
+    }
+
+    fn gamepads(fake: &FakePads) -> Gamepads {
+        let mut gamepads = Gamepads::default();
+        gamepads.set_source(Box::new(fake.clone()));
+        gamepads
+    }
+
+    #[test]
+    fn button_down_pressed_released() {
+        let fake = FakePads::default();
+        let mut gamepads = gamepads(&fake);
+
+        *fake.0.borrow_mut() = vec![GamepadReport::new(7).button(GamepadButton::South, true)];
+        gamepads.poll();
+        let pad = gamepads.pad(7).unwrap();
+        assert!(pad.is_button_down(GamepadButton::South));
+        assert!(pad.is_button_pressed(GamepadButton::South));
+        assert!(!pad.is_button_down(GamepadButton::East));
+
+        gamepads.poll();
+        let pad = gamepads.pad(7).unwrap();
+        assert!(pad.is_button_down(GamepadButton::South));
+        assert!(!pad.is_button_pressed(GamepadButton::South));
+
+        *fake.0.borrow_mut() = vec![GamepadReport::new(7)];
+        gamepads.poll();
+        let pad = gamepads.pad(7).unwrap();
+        assert!(!pad.is_button_down(GamepadButton::South));
+        assert!(pad.is_button_released(GamepadButton::South));
+    }
+
+    #[test]
+    fn disconnect() {
+        let fake = FakePads::default();
+        let mut gamepads = gamepads(&fake);
+        *fake.0.borrow_mut() = vec![GamepadReport::new(1), GamepadReport::new(2)];
+        gamepads.poll();
+        assert_eq!(gamepads.pads().len(), 2);
+
+        *fake.0.borrow_mut() = vec![GamepadReport::new(2)];
+        gamepads.poll();
+        assert!(gamepads.pad(1).is_none());
+        assert_eq!(gamepads.pads()[0].id(), 2);
+    }
+
+    #[test]
+    fn dead_zones() {
+        let fake = FakePads::default();
+        let mut gamepads = gamepads(&fake);
+        gamepads.set_dead_zones(0.2, 0.1);
+        *fake.0.borrow_mut() = vec![GamepadReport::new(0)
+            .axis(GamepadAxis::LeftX, 0.1)
+            .axis(GamepadAxis::LeftY, 0.1)
+            .axis(GamepadAxis::RightX, 0.6)
+            .axis(GamepadAxis::LeftTrigger, 0.05)
+            .axis(GamepadAxis::RightTrigger, 1.0)];
+        gamepads.poll();
+        let pad = gamepads.pad(0).unwrap();
+        assert_eq!(pad.stick(GamepadSide::Left), Vec2::ZERO);
+        assert!((pad.stick(GamepadSide::Right) - vec2(0.5, 0.0)).length() < 1e-5);
+        assert_eq!(pad.trigger(GamepadSide::Left), 0.0);
+        assert_eq!(pad.trigger(GamepadSide::Right), 1.0);
+        assert_eq!(pad.raw_axis(GamepadAxis::LeftX), 0.1);
+    }
+
+    #[test]
+    fn dead_zones_are_clamped() {
+        let fake = FakePads::default();
+        let mut gamepads = gamepads(&fake);
+        gamepads.set_dead_zones(1.5, 1.0);
+        *fake.0.borrow_mut() = vec![GamepadReport::new(0)
+            .axis(GamepadAxis::LeftX, 1.0)
+            .axis(GamepadAxis::LeftTrigger, 1.0)
+            .axis(GamepadAxis::RightTrigger, 0.9)];
+        gamepads.poll();
+        let pad = gamepads.pad(0).unwrap();
+        assert_eq!(pad.stick(GamepadSide::Left), vec2(1.0, 0.0));
+        assert_eq!(pad.trigger(GamepadSide::Left), 1.0);
+        assert_eq!(pad.trigger(GamepadSide::Right), 0.0);
+    }
+}
@@ -0,0 +1,497 @@
+use crate::OgtError;
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+/// Compares rendered images against reference PNGs in a directory, see
+/// [`crate::SoftwareBackend::render`] for producing them.
+///
+/// Failures write `<name>.actual.png` and `<name>.diff.png` next to the reference, the diff
+/// marks offending pixels red. Setting the environment variable `OGT_BLESS` (or
+/// [`Golden::bless`]) overwrites the references with the rendered images instead.
+///
+/// ```no_run
+/// # use ogt::*;
+/// let image = SoftwareBackend::render(64, 64, |ctx| ctx.clear_screen(RED));
+/// Golden::new("tests/golden").tolerance(2).assert("red", &image);
+/// ```
+pub struct Golden {
+    directory: PathBuf,
+    tolerance: u8,
+    bless: bool,
+}
+
+impl Golden {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            tolerance: 0,
+            bless: std::env::var_os("OGT_BLESS").is_some_and(|value| value != "0"),
+        }
+    }
+
+    /// Largest difference per color channel that still counts as equal.
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    fn path(&self, name: &str, suffix: &str) -> PathBuf {
+        self.directory.join(format!("{name}{suffix}.png"))
+    }
+
+    fn save(image: &RgbaImage, path: &Path) -> Result<(), OgtError> {
+        Ok(image.save(path)?)
+    }
+
+    /// Compares `image` with the reference `name`.png. A missing reference is an
+    /// [`OgtError::Image`], differing pixels an [`OgtError::InvalidData`].
+    pub fn check(&self, name: &str, image: &RgbaImage) -> Result<(), OgtError> {
+        let reference_path = self.path(name, "");
+        let actual_path = self.path(name, ".actual");
+        let diff_path = self.path(name, ".diff");
+        // Leftovers of an earlier failure would be misleading
+        let _ = std::fs::remove_file(&actual_path);
+        let _ = std::fs::remove_file(&diff_path);
+
+        if self.bless {
+            std::fs::create_dir_all(&self.directory)?;
+            return Self::save(image, &reference_path);
+        }
+
+        let reference = match image::open(&reference_path) {
+            Ok(reference) => reference.into_rgba8(),
+            Err(err) => {
+                Self::save(image, &actual_path)?;
+                return Err(err.into());
+            }
+        };
+        if reference.dimensions() != image.dimensions() {
+            Self::save(image, &actual_path)?;
+            return Err(OgtError::InvalidData(format!(
+                "Expected a {:?} image, got {:?}",
+                reference.dimensions(),
+                image.dimensions()
+            )));
+        }
+
+        let mut diff = RgbaImage::new(image.width(), image.height());
+        let mut mismatches = 0;
+        let mut max_difference = 0;
+        for ((actual, expected), marker) in image
+            .pixels()
+            .zip(reference.pixels())
+            .zip(diff.pixels_mut())
+        {
+            let difference = actual
+                .0
+                .iter()
+                .zip(expected.0)
+                .map(|(a, e)| a.abs_diff(e))
+                .max()
+                .unwrap_or(0);
+            max_difference = max_difference.max(difference);
+            *marker = if difference > self.tolerance {
+                mismatches += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                // Faded so the red markers stand out
+                let luma = actual.0[..3].iter().map(|&c| c as u32).sum::<u32>() / 9;
+                Rgba([luma as u8, luma as u8, luma as u8, 255])
+            };
+        }
+        if mismatches == 0 {
+            return Ok(());
+        }
+        Self::save(image, &actual_path)?;
+        Self::save(&diff, &diff_path)?;
+        Err(OgtError::InvalidData(format!(
+            "{mismatches} pixels differ by more than {} (max {max_difference}), see {}",
+            self.tolerance,
+            diff_path.display()
+        )))
+    }
+
+    /// Panicking version of [`Golden::check`] for tests.
+    #[track_caller]
+    pub fn assert(&self, name: &str, image: &RgbaImage) {
+        if let Err(err) = self.check(name, image) {
+            panic!("{name}: {err}, run with OGT_BLESS=1 to update the reference");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::math::*;
+    use crate::*;
+
+    fn golden() -> Golden {
+        Golden::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"))
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ogt-golden-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn compares_with_tolerance_and_writes_diff() {
+        let dir = temp_dir("compare");
+        let mut image = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        Golden::new(&dir)
+            .bless(true)
+            .check("image", &image)
+            .unwrap();
+        let golden = Golden::new(&dir).bless(false);
+        assert!(matches!(
+            golden.check("missing", &image),
+            Err(OgtError::Image(_))
+        ));
+        assert!(dir.join("missing.actual.png").exists());
+
+        image.put_pixel(1, 2, Rgba([103, 100, 100, 255]));
+        let tolerant = Golden::new(&dir).bless(false).tolerance(3);
+        assert!(tolerant.check("image", &image).is_ok());
+        let err = golden.check("image", &image).unwrap_err();
+        assert!(
+            matches!(&err, OgtError::InvalidData(message) if message.contains("1 pixels differ")),
+            "{err}"
+        );
+        let diff = image::open(dir.join("image.diff.png"))
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(diff.get_pixel(1, 2).0, [255, 0, 0, 255]);
+        assert_ne!(diff.get_pixel(0, 0).0, [255, 0, 0, 255]);
+
+        image.put_pixel(1, 2, Rgba([100, 100, 100, 255]));
+        golden.check("image", &image).unwrap();
+        assert!(!dir.join("image.diff.png").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn projection(ctx: &Context) -> Mat4 {
+        let size = ctx.screen_size().as_vec2();
+        Mat4::orthographic_rh_gl(0.0, size.x, size.y, 0.0, -1.0, 1.0)
+    }
+
+    fn font(ctx: &Context) -> Font {
+        LoadedFont::from_bytes(include_bytes!("../examples/Hack-Regular.ttf"))
+            .create_font(ctx, 16.0)
+    }
+
+    fn shapes_scene(ctx: &mut Context) {
+        ctx.clear_screen(Color::rgb(20, 20, 40));
+        let mut shapes = ShapeBatch::new(ctx);
+        shapes.set_model_view_projection_matrix(projection(ctx));
+        shapes.add_filled_rect(ctx, vec2(4.0, 4.0), vec2(40.0, 28.0), RED);
+        shapes.add_rect(ctx, vec2(50.5, 6.5), vec2(90.5, 26.5), 2.0, YELLOW);
+        shapes.add_line(ctx, vec2(4.0, 60.0), vec2(92.0, 34.0), 3.0, LIGHT_GRAY);
+        let pi = std::f32::consts::PI;
+        shapes.add_filled_circle(
+            ctx,
+            vec2(24.0, 44.0),
+            14.0,
+            32,
+            0.0,
+            2.0 * pi,
+            Color::rgba(0, 255, 0, 160),
+        );
+        shapes.add_circle(
+            ctx,
+            vec2(70.0, 46.0),
+            12.0,
+            1.5,
+            24,
+            0.0,
+            1.5 * pi,
+            LIGHT_BLUE,
+        );
+        shapes.draw(ctx);
+    }
+
+    fn sprites_scene(ctx: &mut Context) {
+        ctx.clear_screen(BLACK);
+        let thing = image::load_from_memory(include_bytes!("../ogt_thing.png"))
+            .unwrap()
+            .into_rgba8();
+        let texture =
+            TextureBuilder::from_bytes(thing.as_raw(), thing.width(), thing.height()).build(ctx);
+        let region = texture.as_region();
+        let mut sprites = SpriteBatch::new(ctx);
+        sprites.set_model_view_projection_matrix(projection(ctx));
+        sprites.add(ctx, &texture, region, WHITE, Vec2::ZERO, vec2(2.0, 2.0));
+        sprites.add(
+            ctx,
+            &texture,
+            region,
+            LIGHT_RED,
+            Vec2::ZERO,
+            vec3(40.0, 2.0, 0.0),
+        );
+        let size = vec2(region.bottom_right[0], region.bottom_right[1]);
+        sprites.add(
+            ctx,
+            &texture,
+            region,
+            Color::rgba(255, 255, 255, 128),
+            size / 2.0,
+            Affine2::from_scale_angle_translation(vec2(0.75, 0.75), 0.5, vec2(50.0, 20.0)),
+        );
+        sprites.draw(ctx);
+    }
+
+    fn text_scene(ctx: &mut Context) {
+        ctx.clear_screen(BLACK);
+        let font = font(ctx);
+        let mut sprites = SpriteBatch::new(ctx);
+        let size = ctx.screen_size().as_vec2();
+        sprites.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+            0.0, size.x, 0.0, size.y, -1.0, 1.0,
+        ));
+        font.draw_text(ctx, &mut sprites, "Hello, ogt!", vec2(4.0, 18.0), WHITE);
+        font.draw_text(ctx, &mut sprites, "0123456789", vec2(4.0, 2.0), YELLOW);
+        sprites.draw(ctx);
+    }
+
+    fn ui_scene(ctx: &mut Context) {
+        ctx.clear_screen(Color::rgb(30, 30, 30));
+        let mut ui = Ui::new(ctx, font(ctx));
+        let size = ctx.screen_size().as_vec2();
+        ui.update_matrix(Mat4::orthographic_rh_gl(
+            0.0, size.x, 0.0, size.y, -1.0, 1.0,
+        ));
+        let (play, quit) = (Button::new("Play"), Button::new("Quit"));
+        let menu = VerticalLayout::new(vec![&play as &dyn LayoutElement, &quit]).gap(4.0);
+        let layout = CenterLayout::new(menu);
+        layout.layout(&ui, Vec2::ZERO);
+        layout.set_rect(&ui, Vec2::ZERO, size);
+        ui.render(ctx, &layout);
+        ui.draw(ctx);
+    }
+
+    fn render_target_scene(ctx: &mut Context) {
+        let target = RenderTargetBuilder::new(24, 16)
+            .filter(TextureFilter::Nearest)
+            .build(ctx);
+        ctx.set_render_target(Some(&target));
+        ctx.clear_screen(Color::rgb(20, 20, 40));
+        let mut shapes = ShapeBatch::new(ctx);
+        shapes.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+            0.0, 24.0, 16.0, 0.0, -1.0, 1.0,
+        ));
+        shapes.add_filled_rect(ctx, vec2(1.0, 1.0), vec2(9.0, 5.0), RED);
+        shapes.add_filled_circle(
+            ctx,
+            vec2(16.0, 10.0),
+            5.0,
+            16,
+            0.0,
+            2.0 * std::f32::consts::PI,
+            YELLOW,
+        );
+        shapes.draw(ctx);
+        ctx.set_render_target(None);
+
+        ctx.clear_screen(BLACK);
+        let mut sprites = SpriteBatch::new(ctx);
+        let size = ctx.screen_size().as_vec2();
+        sprites.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+            0.0, size.x, 0.0, size.y, -1.0, 1.0,
+        ));
+        let region = target.texture().as_region();
+        sprites.add(
+            ctx,
+            target.texture(),
+            region,
+            WHITE,
+            Vec2::ZERO,
+            Affine2::from_scale(vec2(4.0, 4.0)),
+        );
+        sprites.draw(ctx);
+    }
+
+    fn blend_modes_scene(ctx: &mut Context) {
+        ctx.clear_screen(Color::rgb(40, 80, 160));
+        let mut shapes = ShapeBatch::new(ctx);
+        shapes.set_model_view_projection_matrix(projection(ctx));
+        shapes.add_filled_rect(ctx, vec2(0.0, 16.0), vec2(96.0, 32.0), LIGHT_GRAY);
+        let modes = [
+            BlendMode::Alpha,
+            BlendMode::PremultipliedAlpha,
+            BlendMode::Additive,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Replace,
+        ];
+        for (i, mode) in modes.into_iter().enumerate() {
+            shapes.set_blend_mode(ctx, mode);
+            let left = 2.0 + i as f32 * 16.0;
+            let color = match mode {
+                BlendMode::PremultipliedAlpha | BlendMode::Multiply => {
+                    Color::rgba(125, 75, 25, 160)
+                }
+                _ => Color::rgba(200, 120, 40, 160),
+            };
+            shapes.add_filled_rect(ctx, vec2(left, 4.0), vec2(left + 12.0, 44.0), color);
+        }
+        shapes.draw(ctx);
+    }
+
+    fn depth_scene(ctx: &mut Context) {
+        ctx.clear_screen(BLACK);
+        let texture = TextureBuilder::from_bytes(&[255; 16 * 16 * 4], 16, 16).build(ctx);
+        let region = texture.as_region();
+        let mut sprites = SpriteBatch::new(ctx);
+        let size = ctx.screen_size().as_vec2();
+        sprites.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+            0.0, size.x, 0.0, size.y, -1.0, 1.0,
+        ));
+        sprites.set_depth_test(ctx, true);
+        // Submitted in the wrong order, higher z is in front
+        let quads = [
+            (vec3(4.0, 4.0, 0.5), RED),
+            (vec3(12.0, 12.0, 0.0), BLUE),
+            (vec3(14.0, 2.0, -0.5), Color::rgba(255, 255, 255, 128)),
+            (vec3(36.0, 16.0, 0.75), Color::rgba(0, 255, 0, 128)),
+            (vec3(44.0, 8.0, 0.25), Color::rgba(255, 255, 0, 128)),
+            (vec3(40.0, 2.0, 0.5), RED),
+        ];
+        for (position, color) in quads {
+            sprites.add(ctx, &texture, region, color, Vec2::ZERO, position);
+        }
+        sprites.draw(ctx);
+    }
+
+    fn textures_scene(ctx: &mut Context) {
+        ctx.clear_screen(BLACK);
+        let font = font(ctx);
+        let checker = TextureBuilder::from_bytes(&[RED.0, WHITE.0, WHITE.0, RED.0].concat(), 2, 2)
+            .filter(TextureFilter::Nearest)
+            .build(ctx);
+        let stripes = TextureBuilder::from_bytes(&[BLUE.0, YELLOW.0].concat(), 1, 2)
+            .filter(TextureFilter::Nearest)
+            .build(ctx);
+        let mut sprites = SpriteBatch::new(ctx);
+        let size = ctx.screen_size().as_vec2();
+        sprites.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+            0.0, size.x, 0.0, size.y, -1.0, 1.0,
+        ));
+        // Icons and text interleaved, all in one draw call
+        let icon = |position| Affine2::from_scale_angle_translation(vec2(6.0, 6.0), 0.0, position);
+        sprites.add(
+            ctx,
+            &checker,
+            checker.as_region(),
+            WHITE,
+            Vec2::ZERO,
+            icon(vec2(2.0, 6.0)),
+        );
+        font.draw_text(ctx, &mut sprites, "a", vec2(16.0, 6.0), WHITE);
+        sprites.add(
+            ctx,
+            &stripes,
+            stripes.as_region(),
+            WHITE,
+            Vec2::ZERO,
+            icon(vec2(28.0, 6.0)),
+        );
+        font.draw_text(ctx, &mut sprites, "b", vec2(36.0, 6.0), GREEN);
+        sprites.add(
+            ctx,
+            &checker,
+            checker.as_region(),
+            WHITE,
+            Vec2::ZERO,
+            icon(vec2(48.0, 6.0)),
+        );
+        sprites.draw(ctx);
+    }
+
+    fn instanced_scene(ctx: &mut Context) {
+        ctx.clear_screen(BLACK);
+        let texture = TextureBuilder::from_bytes(&[RED.0, WHITE.0, WHITE.0, BLUE.0].concat(), 2, 2)
+            .filter(TextureFilter::Nearest)
+            .build(ctx);
+        let region = texture.as_region();
+        let mut sprites = InstancedSpriteBatch::new(ctx);
+        let size = ctx.screen_size().as_vec2();
+        sprites.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+            0.0, size.x, 0.0, size.y, -1.0, 1.0,
+        ));
+        let origin = vec2(1.0, 1.0);
+        sprites.add(
+            ctx,
+            &texture,
+            region,
+            WHITE,
+            Vec2::ZERO,
+            vec2(2.0, 2.0),
+            0.0,
+            vec2(8.0, 8.0),
+        );
+        sprites.add(
+            ctx,
+            &texture,
+            region,
+            YELLOW,
+            origin,
+            vec2(32.3, 16.2),
+            0.6,
+            vec2(7.0, 5.0),
+        );
+        sprites.add(
+            ctx,
+            &texture,
+            region,
+            Color::rgba(255, 255, 255, 128),
+            origin,
+            vec2(52.2, 20.3),
+            -1.1,
+            vec2(6.0, 6.0),
+        );
+        sprites.draw(ctx);
+    }
+
+    type Scene = fn(&mut Context);
+
+    const SCENES: [(&str, u32, u32, Scene); 9] = [
+        ("shapes", 96, 64, shapes_scene),
+        ("sprites", 96, 64, sprites_scene),
+        ("text", 160, 32, text_scene),
+        ("ui", 128, 96, ui_scene),
+        ("render_target", 96, 64, render_target_scene),
+        ("blend_modes", 96, 48, blend_modes_scene),
+        ("depth", 64, 40, depth_scene),
+        ("textures", 64, 24, textures_scene),
+        ("instanced", 64, 32, instanced_scene),
+    ];
+
+    #[test]
+    fn scenes() {
+        for (name, width, height, scene) in SCENES {
+            let image = SoftwareBackend::render(width, height, scene);
+            golden().assert(name, &image);
+        }
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn gl_matches_references() {
+        for (name, width, height, scene) in SCENES {
+            let Some(mut headless) = Headless::for_gl_test(width, height) else {
+                return;
+            };
+            scene(headless.context());
+            let image = headless.read_pixels();
+            // Texture filtering is less precise on GPUs
+            golden().bless(false).tolerance(3).assert(name, &image);
+        }
+    }
+}
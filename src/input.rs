@@ -0,0 +1,132 @@
+use crate::math::Vec2;
+use crate::{KeyCode, KeyMods, MouseButton};
+pub use miniquad::TouchPhase;
+
+/// Raw input as delivered by the window, positions are in window coordinates.
+/// See [`crate::Recording`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    MouseMotion(Vec2),
+    /// Hardware motion delta, see [`crate::Context::raw_mouse_delta`].
+    RawMouseMotion(Vec2),
+    MouseDown(MouseButton, Vec2),
+    MouseUp(MouseButton, Vec2),
+    MouseWheel(Vec2),
+    /// The flag is set for auto-repeats.
+    KeyDown(KeyCode, KeyMods, bool),
+    KeyUp(KeyCode, KeyMods),
+    Char(char, KeyMods),
+    Touch(TouchPhase, u64, Vec2),
+}
+
+/// A finger on the screen, see [`crate::Context::touches`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Touch {
+    pub id: u64,
+    /// Last phase reported for this touch.
+    pub phase: TouchPhase,
+    /// Position in window coordinates.
+    pub position: Vec2,
+}
+
+/// One entry of the typed text stream, see [`crate::Context::text_input`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextInput {
+    Char(char),
+    Backspace,
+    Delete,
+    Enter,
+    Tab,
+    Escape,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+}
+
+impl TextInput {
+    pub(crate) fn from_char(c: char) -> Option<Self> {
+        // Control characters are reported through key events instead
+        (!c.is_control()).then_some(TextInput::Char(c))
+    }
+
+    pub(crate) fn from_key(key: KeyCode) -> Option<Self> {
+        Some(match key {
+            KeyCode::Backspace => TextInput::Backspace,
+            KeyCode::Delete => TextInput::Delete,
+            KeyCode::Enter | KeyCode::KpEnter => TextInput::Enter,
+            KeyCode::Tab => TextInput::Tab,
+            KeyCode::Escape => TextInput::Escape,
+            KeyCode::Left => TextInput::Left,
+            KeyCode::Right => TextInput::Right,
+            KeyCode::Up => TextInput::Up,
+            KeyCode::Down => TextInput::Down,
+            KeyCode::Home => TextInput::Home,
+            KeyCode::End => TextInput::End,
+            KeyCode::PageUp => TextInput::PageUp,
+            KeyCode::PageDown => TextInput::PageDown,
+            _ => return None,
+        })
+    }
+
+    /// Simple line editing: appends characters and handles backspace, ignores everything else.
+    /// Returns `true` if `text` was changed.
+    pub fn apply(&self, text: &mut String) -> bool {
+        match *self {
+            TextInput::Char(c) => {
+                text.push(c);
+                true
+            }
+            TextInput::Backspace => text.pop().is_some(),
+            _ => false,
+        }
+    }
+}
+
+macro_rules! key_codes {
+    ($($key:ident),* $(,)?) => {
+        &[$(KeyCode::$key),*]
+    };
+}
+
+#[rustfmt::skip]
+pub(crate) const KEY_CODES: &[KeyCode] = key_codes![
+    Space, Apostrophe, Comma, Minus, Period, Slash, Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7,
+    Key8, Key9, Semicolon, Equal, A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V,
+    W, X, Y, Z, LeftBracket, Backslash, RightBracket, GraveAccent, World1, World2, Escape, Enter,
+    Tab, Backspace, Insert, Delete, Right, Left, Down, Up, PageUp, PageDown, Home, End, CapsLock,
+    ScrollLock, NumLock, PrintScreen, Pause, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13,
+    F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25, Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6,
+    Kp7, Kp8, Kp9, KpDecimal, KpDivide, KpMultiply, KpSubtract, KpAdd, KpEnter, KpEqual, LeftShift,
+    LeftControl, LeftAlt, LeftSuper, RightShift, RightControl, RightAlt, RightSuper, Menu, Back,
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edit_line() {
+        let mut text = String::new();
+        for input in "ab".chars().filter_map(TextInput::from_char).chain([
+            TextInput::Backspace,
+            TextInput::Left,
+            TextInput::Char('ü'),
+        ]) {
+            input.apply(&mut text);
+        }
+        assert_eq!(text, "aü");
+        assert!(!TextInput::Backspace.apply(&mut String::new()));
+    }
+
+    #[test]
+    fn control_chars_are_not_text() {
+        assert_eq!(TextInput::from_char('\u{8}'), None);
+        assert_eq!(TextInput::from_char('\r'), None);
+        assert_eq!(TextInput::from_key(KeyCode::A), None);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::backend::as_bytes;
+use crate::{
+    math::*, Backend, BlendMode, BufferId, BufferKind, BufferUsage, Color, Context, DepthMode,
+    DrawCall, OgtError, Region, Shader, SpriteBatch, Texture, Uniform, VertexAttribute,
+    VertexFormat, VertexLayout,
+};
+use memoffset::offset_of;
+use std::mem::size_of;
+use std::rc::Rc;
+
+/// Everything the vertex shader needs to expand one sprite
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Instance {
+    position: [f32; 2],
+    rotation: f32,
+    scale: [f32; 2],
+    origin: [f32; 2],
+    size: [f32; 2],
+    /// Texture coordinates of the bottom left and top right corner
+    uv: [f32; 4],
+    color: [u8; 4],
+}
+
+/// Like [`SpriteBatch`], but only uploads one small record per sprite and lets the GPU build
+/// the quads. Pays off with many sprites, e.g. particles. All sprites of one draw call share a
+/// texture, the batch draws whenever it changes.
+///
+/// Backends without [`Backend::supports_instancing`] get the sprites through a
+/// [`SpriteBatch`] instead, with the same result.
+pub struct InstancedSpriteBatch {
+    max_sprites: usize,
+    backend: Rc<dyn Backend>,
+    /// Corners of the unit quad
+    vertex_buffer: BufferId,
+    index_buffer: BufferId,
+    instance_buffer: BufferId,
+    instances: Vec<Instance>,
+    texture: Option<Texture>,
+    shader: Shader,
+    blend_mode: BlendMode,
+    model_view_projection: Mat4,
+    fallback: Option<SpriteBatch>,
+}
+
+impl InstancedSpriteBatch {
+    pub fn new(context: &Context) -> Self {
+        Self::with_max_sprites(context, 10_000)
+    }
+
+    pub fn try_new(context: &Context) -> Result<Self, OgtError> {
+        Self::try_with_max_sprites(context, 10_000)
+    }
+
+    /// Panics if the shader can't be compiled, see [`InstancedSpriteBatch::try_with_max_sprites`].
+    pub fn with_max_sprites(context: &Context, max: usize) -> Self {
+        Self::try_with_max_sprites(context, max).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_with_max_sprites(context: &Context, max: usize) -> Result<Self, OgtError> {
+        let backend = context.backend().clone();
+        let shader = Shader::new(context, VERTEX_SHADER, FRAGMENT_SHADER, &layout())?;
+        let fallback = match backend.supports_instancing() {
+            true => None,
+            false => Some(SpriteBatch::try_with_max_sprites(context, max)?),
+        };
+        let vertex_buffer = backend.create_buffer(BufferKind::Vertex, BufferUsage::Static);
+        backend.update_buffer(
+            vertex_buffer,
+            as_bytes(&[[0.0f32, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]),
+        );
+        let index_buffer = backend.create_buffer(BufferKind::Index, BufferUsage::Static);
+        backend.update_buffer(index_buffer, as_bytes(&[0u16, 1, 3, 1, 2, 3]));
+        let instance_buffer = backend.create_buffer(BufferKind::Vertex, BufferUsage::Stream);
+        Ok(Self {
+            max_sprites: max,
+            backend,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            instances: Vec::with_capacity(max),
+            texture: None,
+            shader,
+            blend_mode: BlendMode::Alpha,
+            model_view_projection: Mat4::IDENTITY,
+            fallback,
+        })
+    }
+
+    /// Adds the `sprite` region of `texture`, scaled and rotated around `origin` which ends up
+    /// at `position`. `origin` is in pixels from the bottom left of the region.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add(
+        &mut self,
+        context: &Context,
+        texture: &Texture,
+        sprite: Region,
+        color: Color,
+        origin: Vec2,
+        position: Vec2,
+        rotation: f32,
+        scale: Vec2,
+    ) {
+        if let Some(fallback) = &mut self.fallback {
+            let transform =
+                Affine2::from_scale_angle_translation(scale, rotation, position - origin);
+            fallback.add(context, texture, sprite, color, origin, transform);
+            return;
+        }
+        if self.texture.as_ref() != Some(texture) {
+            self.draw(context);
+            self.texture = Some(texture.clone());
+        } else if self.instances.len() == self.max_sprites {
+            self.draw(context);
+        }
+        let (width, height) = (texture.width as f32, texture.height as f32);
+        let (mut top, mut bottom) = (sprite.top_left[1] / height, sprite.bottom_right[1] / height);
+        if texture.is_flipped() {
+            (top, bottom) = (1.0 - top, 1.0 - bottom);
+        }
+        self.instances.push(Instance {
+            position: position.to_array(),
+            rotation,
+            scale: scale.to_array(),
+            origin: origin.to_array(),
+            size: [
+                sprite.bottom_right[0] - sprite.top_left[0],
+                sprite.bottom_right[1] - sprite.top_left[1],
+            ],
+            uv: [
+                sprite.top_left[0] / width,
+                bottom,
+                sprite.bottom_right[0] / width,
+                top,
+            ],
+            color: color.0,
+        });
+    }
+
+    pub fn draw(&mut self, context: &Context) {
+        if let Some(fallback) = &mut self.fallback {
+            fallback.draw(context);
+            return;
+        }
+        let Some(texture) = &self.texture else {
+            return;
+        };
+        if self.instances.is_empty() {
+            return;
+        }
+        self.backend
+            .update_buffer(self.instance_buffer, as_bytes(&self.instances));
+        self.shader.draw(&DrawCall {
+            shader: self.shader.id(),
+            vertices: self.vertex_buffer,
+            indices: Some(self.index_buffer),
+            count: 6,
+            instances: Some((self.instance_buffer, self.instances.len())),
+            textures: &[texture.id()],
+            uniforms: &[(
+                "viewProjectionMatrix",
+                Uniform::Mat4(self.model_view_projection),
+            )],
+            blend: self.blend_mode,
+            depth: DepthMode::Disabled,
+        });
+        self.instances.clear();
+    }
+
+    /// How many sprites are drawn at once at most.
+    pub fn max_sprites(&self) -> usize {
+        self.max_sprites
+    }
+
+    pub fn set_model_view_projection_matrix(&mut self, matrix: Mat4) {
+        self.model_view_projection = matrix;
+        if let Some(fallback) = &mut self.fallback {
+            fallback.set_model_view_projection_matrix(matrix);
+        }
+    }
+
+    /// How sprites are blended with the background, [`BlendMode::Alpha`] by default.
+    /// Pending sprites are drawn first if the mode changes.
+    pub fn set_blend_mode(&mut self, context: &Context, mode: BlendMode) {
+        if mode != self.blend_mode {
+            self.draw(context);
+            self.blend_mode = mode;
+        }
+        if let Some(fallback) = &mut self.fallback {
+            fallback.set_blend_mode(context, mode);
+        }
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+}
+
+impl Drop for InstancedSpriteBatch {
+    fn drop(&mut self) {
+        self.backend.delete_buffer(self.vertex_buffer);
+        self.backend.delete_buffer(self.index_buffer);
+        self.backend.delete_buffer(self.instance_buffer);
+    }
+}
+
+fn layout() -> VertexLayout {
+    let attribute = |name, format, offset| VertexAttribute {
+        name,
+        format,
+        offset,
+    };
+    VertexLayout {
+        stride: size_of::<[f32; 2]>(),
+        attributes: vec![attribute("corner", VertexFormat::Float2, 0)],
+        instance_stride: size_of::<Instance>(),
+        instance_attributes: vec![
+            attribute(
+                "instance_position",
+                VertexFormat::Float2,
+                offset_of!(Instance, position),
+            ),
+            attribute(
+                "instance_rotation",
+                VertexFormat::Float1,
+                offset_of!(Instance, rotation),
+            ),
+            attribute(
+                "instance_scale",
+                VertexFormat::Float2,
+                offset_of!(Instance, scale),
+            ),
+            attribute(
+                "instance_origin",
+                VertexFormat::Float2,
+                offset_of!(Instance, origin),
+            ),
+            attribute(
+                "instance_size",
+                VertexFormat::Float2,
+                offset_of!(Instance, size),
+            ),
+            attribute(
+                "instance_uv",
+                VertexFormat::Float4,
+                offset_of!(Instance, uv),
+            ),
+            attribute(
+                "instance_color",
+                VertexFormat::UByte4Norm,
+                offset_of!(Instance, color),
+            ),
+        ],
+    }
+}
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec2 corner;
+attribute vec2 instance_position;
+attribute float instance_rotation;
+attribute vec2 instance_scale;
+attribute vec2 instance_origin;
+attribute vec2 instance_size;
+attribute vec4 instance_uv;
+attribute vec4 instance_color;
+
+varying lowp vec4 fragmentColor;
+varying lowp vec2 texCoord;
+
+uniform mat4 viewProjectionMatrix;
+
+void main() {
+    vec2 local = (corner * instance_size - instance_origin) * instance_scale;
+    float s = sin(instance_rotation);
+    float c = cos(instance_rotation);
+    vec2 position = instance_position + vec2(c * local.x - s * local.y, s * local.x + c * local.y);
+    gl_Position = viewProjectionMatrix * vec4(position, 0.0, 1.0);
+    fragmentColor = instance_color;
+    texCoord = mix(instance_uv.xy, instance_uv.zw, corner);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 100
+varying lowp vec4 fragmentColor;
+varying lowp vec2 texCoord;
+
+uniform sampler2D Tex;
+
+void main() {
+    gl_FragColor = fragmentColor * texture2D(Tex, texCoord);
+}
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{RenderTargetBuilder, TextureBuilder, RED};
+
+    #[test]
+    fn instance_is_compact() {
+        assert_eq!(size_of::<Instance>(), 56);
+    }
+
+    #[test]
+    fn instances_describe_sprites() {
+        let context = Context::default();
+        let mut batch = InstancedSpriteBatch::new(&context);
+        let texture = TextureBuilder::from_bytes(&[255; 8 * 4 * 4], 8, 4).build(&context);
+        let target = RenderTargetBuilder::new(8, 4).build(&context);
+        let region = Region {
+            top_left: [2.0, 1.0],
+            bottom_right: [6.0, 4.0],
+        };
+        let add = |batch: &mut InstancedSpriteBatch, texture| {
+            let (origin, position, scale) = (vec2(1.0, 2.0), vec2(10.0, 20.0), vec2(2.0, 3.0));
+            batch.add(&context, texture, region, RED, origin, position, 0.5, scale);
+            batch.instances[0]
+        };
+
+        let instance = add(&mut batch, &texture);
+        assert_eq!(instance.position, [10.0, 20.0]);
+        assert_eq!(instance.rotation, 0.5);
+        assert_eq!(instance.scale, [2.0, 3.0]);
+        assert_eq!(instance.origin, [1.0, 2.0]);
+        assert_eq!(instance.size, [4.0, 3.0]);
+        // Bottom left and top right, the rows of a texture are stored top first
+        assert_eq!(instance.uv, [0.25, 1.0, 0.75, 0.25]);
+        assert_eq!(instance.color, RED.0);
+
+        // Drawing the previous texture first
+        let instance = add(&mut batch, target.texture());
+        assert_eq!(batch.instances.len(), 1);
+        assert_eq!(instance.size, [4.0, 3.0]);
+        assert_eq!(instance.uv, [0.25, 0.0, 0.75, 0.75]);
+    }
+}
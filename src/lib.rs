@@ -1,28 +1,66 @@
 pub use miniquad::error;
 pub use miniquad::fs::load_file;
-pub use miniquad::MouseButton;
+pub use miniquad::{CursorIcon, KeyCode, KeyMods, MouseButton};
 
+pub use actions::*;
 pub use assets::*;
+#[cfg(feature = "miniquad")]
+pub use backend::GlBackend;
+#[cfg(feature = "headless")]
+pub use backend::Headless;
+pub use backend::{
+    Backend, BlendMode, BufferId, BufferKind, BufferUsage, DepthMode, DrawCall, RenderTargetId,
+    ShaderId, SoftwareBackend, TextureFilter, TextureId, Uniform, VertexAttribute, VertexFormat,
+    VertexLayout,
+};
+pub use error::*;
 pub use font::*;
+pub use gamepad::*;
+pub use golden::*;
+pub use input::*;
+pub use instanced_sprite_batch::*;
 use miniquad::window::screen_size;
-use miniquad::{conf, date, start, EventHandler};
+use miniquad::{date, start, EventHandler};
+pub use post_process::*;
+pub use render_target::*;
+pub use replay::*;
+pub use shader::*;
 pub use shape_batch::*;
 pub use sprite_batch::*;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
 pub use texture::*;
+pub use timestep::*;
+pub use timing::*;
 pub use ui::*;
+pub use window::*;
 
+use backend::SharedBackend;
 pub use glam as math;
 use math::*;
 
+mod actions;
 mod assets;
 mod backend;
+mod error;
 mod font;
+mod gamepad;
+mod golden;
+mod input;
+mod instanced_sprite_batch;
+mod post_process;
 mod rect_pack;
+mod render_target;
+mod replay;
 mod shader;
 mod shape_batch;
 mod sprite_batch;
 mod texture;
+mod timestep;
+mod timing;
 mod ui;
+mod window;
 
 #[derive(Clone, Copy)]
 pub struct Color([u8; 4]);
@@ -51,62 +89,144 @@ impl Color {
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+/// Maximum time and distance between two clicks to count as a double click.
+const DOUBLE_CLICK_TIME: f64 = 0.4;
+const DOUBLE_CLICK_DISTANCE: f32 = 5.0;
+
 #[derive(Default)]
 struct MouseButtonState {
     down: bool,
     pressed: bool,
+    released: bool,
+    double_clicked: bool,
+}
+
+#[derive(Default)]
+struct KeyState {
+    down: bool,
+    pressed: bool,
+    released: bool,
+    repeated: bool,
 }
 
 #[derive(Default)]
 pub struct Context {
     screen_size: UVec2,
-    mouse_position: UVec2,
+    mouse_position: Option<Vec2>,
+    mouse_delta: Vec2,
+    raw_mouse_delta: Vec2,
     mouse_wheel: Vec2,
+    last_click: Option<(MouseButton, f64, Vec2)>,
     left: MouseButtonState,
     right: MouseButtonState,
     middle: MouseButtonState,
+    keys: HashMap<KeyCode, KeyState>,
+    key_mods: KeyMods,
+    text_input: Vec<TextInput>,
+    touches: Vec<Touch>,
+    touches_started: Vec<Touch>,
+    touches_ended: Vec<Touch>,
+    primary_touch: Option<u64>,
+    gamepads: Gamepads,
+    fixed_timestep: FixedTimestep,
+    interpolation_alpha: f32,
+    suspended: bool,
+    frame_stats: FrameStats,
+    accumulator: Accumulator,
+    frame_events: Vec<InputEvent>,
+    recording: Option<Recording>,
+    replay: VecDeque<RecordedFrame>,
+    backend: SharedBackend,
+    headless: bool,
+    quit_requested: Cell<bool>,
+    render_target: RefCell<Option<RenderTarget>>,
 }
 
 impl Context {
-    pub(crate) fn configure_blend(&self) {
-        crate::backend::configure_blend();
+    /// A context rendering through `backend`. [`Context::default`] renders nothing.
+    pub fn with_backend(backend: Rc<dyn Backend>) -> Self {
+        Self {
+            backend: SharedBackend(backend),
+            ..Default::default()
+        }
+    }
+
+    pub fn backend(&self) -> &Rc<dyn Backend> {
+        &self.backend.0
     }
 
     pub fn clear_screen(&self, color: Color) {
-        crate::backend::clear_screen(color);
+        self.backend().clear(color);
     }
 
     pub fn set_viewport(&self, top_left: IVec2, bottom_right: IVec2) {
-        crate::backend::set_viewport(top_left, bottom_right);
+        self.backend().set_viewport(top_left, bottom_right);
+    }
+
+    /// Draws and clears go to `target` until this is called with `None`, which switches back
+    /// to the screen. The viewport is reset to cover the target or the screen.
+    pub fn set_render_target(&self, target: Option<&RenderTarget>) {
+        self.backend()
+            .bind_render_target(target.map(RenderTarget::id));
+        let size = target.map_or(self.screen_size, RenderTarget::size);
+        self.set_viewport(IVec2::ZERO, size.as_ivec2());
+        // Keeps the framebuffer alive while it is bound
+        *self.render_target.borrow_mut() = target.cloned();
+    }
+
+    pub fn render_target(&self) -> Option<RenderTarget> {
+        self.render_target.borrow().clone()
     }
 
     pub fn quit(&self) {
+        if self.headless {
+            self.quit_requested.set(true);
+            return;
+        }
         crate::backend::quit();
     }
 
+    /// See [`Application::suspended`].
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
     pub fn screen_size(&self) -> UVec2 {
         self.screen_size
     }
 
-    pub fn mouse_position(&self) -> UVec2 {
-        self.mouse_position
+    /// Mouse position in window coordinates, can be outside of the window while dragging.
+    pub fn mouse_position(&self) -> Vec2 {
+        self.mouse_position.unwrap_or_default()
+    }
+
+    /// Distance the cursor moved this frame.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    /// Unaccelerated hardware motion this frame, also reported while the cursor is grabbed.
+    /// Use it together with [`Context::set_cursor_grab`] for relative (mouse look) controls.
+    /// The units depend on the platform and are not necessarily pixels.
+    pub fn raw_mouse_delta(&self) -> Vec2 {
+        self.raw_mouse_delta
     }
 
-    fn mouse_button_state(&self, button: MouseButton) -> &MouseButtonState {
+    fn mouse_button_state(&self, button: MouseButton) -> Option<&MouseButtonState> {
         match button {
-            MouseButton::Left => &self.left,
-            MouseButton::Right => &self.right,
-            MouseButton::Middle => &self.middle,
-            _ => panic!(),
+            MouseButton::Left => Some(&self.left),
+            MouseButton::Right => Some(&self.right),
+            MouseButton::Middle => Some(&self.middle),
+            MouseButton::Unknown => None,
         }
     }
 
-    fn mouse_button_state_mut(&mut self, button: MouseButton) -> &mut MouseButtonState {
+    fn mouse_button_state_mut(&mut self, button: MouseButton) -> Option<&mut MouseButtonState> {
         match button {
-            MouseButton::Left => &mut self.left,
-            MouseButton::Right => &mut self.right,
-            MouseButton::Middle => &mut self.middle,
-            _ => panic!(),
+            MouseButton::Left => Some(&mut self.left),
+            MouseButton::Right => Some(&mut self.right),
+            MouseButton::Middle => Some(&mut self.middle),
+            MouseButton::Unknown => None,
         }
     }
 
@@ -115,16 +235,421 @@ impl Context {
     }
 
     pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
-        self.mouse_button_state(button).pressed
+        self.mouse_button_state(button)
+            .is_some_and(|state| state.pressed)
     }
 
     pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
-        self.mouse_button_state(button).down
+        self.mouse_button_state(button)
+            .is_some_and(|state| state.down)
+    }
+
+    pub fn is_mouse_button_released(&self, button: MouseButton) -> bool {
+        self.mouse_button_state(button)
+            .is_some_and(|state| state.released)
+    }
+
+    /// Button was pressed this frame, shortly after a press at about the same position.
+    pub fn is_mouse_button_double_clicked(&self, button: MouseButton) -> bool {
+        self.mouse_button_state(button)
+            .is_some_and(|state| state.double_clicked)
+    }
+
+    pub fn show_mouse(&self, shown: bool) {
+        if !self.headless {
+            crate::backend::show_mouse(shown);
+        }
+    }
+
+    /// Confines the cursor to the window, see [`Context::raw_mouse_delta`].
+    pub fn set_cursor_grab(&self, grab: bool) {
+        if !self.headless {
+            crate::backend::set_cursor_grab(grab);
+        }
+    }
+
+    pub fn set_mouse_cursor(&self, icon: CursorIcon) {
+        if !self.headless {
+            crate::backend::set_mouse_cursor(icon);
+        }
+    }
+
+    fn key_state(&self, key: KeyCode) -> Option<&KeyState> {
+        self.keys.get(&key)
+    }
+
+    /// Key is currently held down.
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.key_state(key).is_some_and(|state| state.down)
+    }
+
+    /// Key went down this frame. Auto-repeats are not reported here, see [`Context::is_key_repeated`].
+    pub fn is_key_pressed(&self, key: KeyCode) -> bool {
+        self.key_state(key).is_some_and(|state| state.pressed)
+    }
+
+    /// Key went up this frame.
+    pub fn is_key_released(&self, key: KeyCode) -> bool {
+        self.key_state(key).is_some_and(|state| state.released)
+    }
+
+    /// The OS sent an auto-repeat for this key this frame.
+    pub fn is_key_repeated(&self, key: KeyCode) -> bool {
+        self.key_state(key).is_some_and(|state| state.repeated)
+    }
+
+    /// Modifier keys as reported by the last key event.
+    pub fn key_mods(&self) -> KeyMods {
+        self.key_mods
+    }
+
+    /// Text typed this frame, in order. Editing and navigation keys (including their
+    /// auto-repeats) are interleaved as separate entries.
+    pub fn text_input(&self) -> &[TextInput] {
+        &self.text_input
+    }
+
+    /// All fingers currently on the screen. Touches ended this frame are still included,
+    /// with phase `Ended` or `Cancelled`.
+    pub fn touches(&self) -> &[Touch] {
+        &self.touches
+    }
+
+    /// Touches that began this frame.
+    pub fn touches_started(&self) -> &[Touch] {
+        &self.touches_started
+    }
+
+    /// Touches that were lifted or cancelled this frame.
+    pub fn touches_ended(&self) -> &[Touch] {
+        &self.touches_ended
+    }
+
+    pub fn touch(&self, id: u64) -> Option<&Touch> {
+        self.touches.iter().find(|touch| touch.id == id)
+    }
+
+    /// Replaces the source of gamepad input, see [`GamepadSource`]. Without the `gilrs`
+    /// feature there is no built-in source for real controllers and no pads are reported.
+    pub fn set_gamepad_source(&mut self, source: impl GamepadSource + 'static) {
+        self.gamepads.set_source(Box::new(source));
+    }
+
+    /// Dead zones for sticks (radial) and triggers, both clamped to the range 0 to 0.95.
+    pub fn set_gamepad_dead_zones(&mut self, stick: f32, trigger: f32) {
+        self.gamepads.set_dead_zones(stick, trigger);
+    }
+
+    pub fn gamepads(&self) -> &Gamepads {
+        &self.gamepads
+    }
+
+    pub fn is_gamepad_button_pressed(&self, pad: u32, button: GamepadButton) -> bool {
+        self.gamepads
+            .pad(pad)
+            .is_some_and(|pad| pad.is_button_pressed(button))
+    }
+
+    pub fn is_gamepad_button_down(&self, pad: u32, button: GamepadButton) -> bool {
+        self.gamepads
+            .pad(pad)
+            .is_some_and(|pad| pad.is_button_down(button))
+    }
+
+    pub fn is_gamepad_button_released(&self, pad: u32, button: GamepadButton) -> bool {
+        self.gamepads
+            .pad(pad)
+            .is_some_and(|pad| pad.is_button_released(button))
+    }
+
+    pub fn gamepad_stick(&self, pad: u32, side: GamepadSide) -> Vec2 {
+        self.gamepads
+            .pad(pad)
+            .map_or(Vec2::ZERO, |pad| pad.stick(side))
+    }
+
+    pub fn gamepad_trigger(&self, pad: u32, side: GamepadSide) -> f32 {
+        self.gamepads.pad(pad).map_or(0.0, |pad| pad.trigger(side))
+    }
+
+    pub fn set_fixed_timestep(&mut self, timestep: FixedTimestep) {
+        self.fixed_timestep = timestep;
+    }
+
+    pub fn fixed_timestep(&self) -> FixedTimestep {
+        self.fixed_timestep
+    }
+
+    /// Fraction of a fixed step that has elapsed since the last [`Application::update`],
+    /// between 0 and 1. Use it in [`Application::render`] to interpolate between the
+    /// previous and the current simulation state.
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.interpolation_alpha
+    }
+
+    /// Starts recording all input and frame times from the next frame on, along with the keys,
+    /// buttons and touches held right now. The recording grows until
+    /// [`Context::stop_recording`] is called.
+    pub fn start_recording(&mut self) {
+        self.frame_events.clear();
+        self.recording = Some(Recording {
+            initial: self.input_state(),
+            frames: Vec::new(),
+        });
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recording.take()
+    }
+
+    /// The input recorded so far, e.g. to save it along with a bug report.
+    pub fn recording(&self) -> Option<&Recording> {
+        self.recording.as_ref()
+    }
+
+    /// Drives the next frames from `recording` instead of live input. Live input is
+    /// ignored until the replay is done, what is held right now is replaced by what was held
+    /// when the recording started.
+    pub fn start_replay(&mut self, recording: Recording) {
+        self.restore_input(&recording.initial);
+        self.replay = recording.frames.into();
+    }
+
+    fn input_state(&self) -> replay::InputState {
+        replay::InputState {
+            mouse_position: self.mouse_position,
+            mouse_buttons: [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+                .into_iter()
+                .filter(|&button| self.is_mouse_button_down(button))
+                .collect(),
+            keys: KEY_CODES
+                .iter()
+                .copied()
+                .filter(|&key| self.is_key_down(key))
+                .collect(),
+            key_mods: self.key_mods,
+            touches: self.touches.clone(),
+            primary_touch: self.primary_touch,
+            gamepads: self.gamepads.reports().to_vec(),
+        }
+    }
+
+    /// Replaces the held input with `state`, nothing counts as pressed or released afterwards.
+    pub(crate) fn restore_input(&mut self, state: &replay::InputState) {
+        self.mouse_position = state.mouse_position;
+        self.last_click = None;
+        for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle] {
+            if let Some(button_state) = self.mouse_button_state_mut(button) {
+                button_state.down = state.mouse_buttons.contains(&button);
+            }
+        }
+        self.keys.clear();
+        for &key in state.keys.iter() {
+            self.keys.entry(key).or_default().down = true;
+        }
+        self.key_mods = state.key_mods;
+        self.touches = state.touches.clone();
+        self.primary_touch = state.primary_touch;
+        self.gamepads.set_reports(&state.gamepads);
+        self.end_frame();
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        !self.replay.is_empty()
+    }
+
+    pub(crate) fn input_event(&mut self, event: InputEvent) {
+        if self.is_replaying() {
+            return;
+        }
+        self.apply_event(&event);
+        if self.recording.is_some() {
+            self.frame_events.push(event);
+        }
+    }
+
+    fn apply_event(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::MouseMotion(position) => self.move_mouse(position),
+            InputEvent::RawMouseMotion(delta) => self.raw_mouse_delta += delta,
+            InputEvent::MouseDown(button, position) => {
+                self.move_mouse(position);
+                let double_clicked = self.last_click.is_some_and(|(last, time, at)| {
+                    last == button
+                        && self.frame_stats.elapsed() - time <= DOUBLE_CLICK_TIME
+                        && at.distance(position) <= DOUBLE_CLICK_DISTANCE
+                });
+                // A third click starts over instead of being another double click
+                self.last_click =
+                    (!double_clicked).then_some((button, self.frame_stats.elapsed(), position));
+                if let Some(button_state) = self.mouse_button_state_mut(button) {
+                    button_state.down = true;
+                    button_state.pressed = true;
+                    button_state.double_clicked |= double_clicked;
+                }
+            }
+            InputEvent::MouseUp(button, position) => {
+                self.move_mouse(position);
+                if let Some(button_state) = self.mouse_button_state_mut(button) {
+                    button_state.down = false;
+                    button_state.released = true;
+                }
+            }
+            InputEvent::MouseWheel(wheel) => self.mouse_wheel = wheel,
+            InputEvent::KeyDown(keycode, keymods, repeat) => {
+                self.key_mods = keymods;
+                let key_state = self.keys.entry(keycode).or_default();
+                key_state.down = true;
+                if repeat {
+                    key_state.repeated = true;
+                } else {
+                    key_state.pressed = true;
+                }
+                self.text_input.extend(TextInput::from_key(keycode));
+            }
+            InputEvent::KeyUp(keycode, keymods) => {
+                self.key_mods = keymods;
+                let key_state = self.keys.entry(keycode).or_default();
+                key_state.down = false;
+                key_state.released = true;
+            }
+            InputEvent::Char(character, keymods) => {
+                self.key_mods = keymods;
+                self.text_input.extend(TextInput::from_char(character));
+            }
+            InputEvent::Touch(phase, id, position) => {
+                let touch = Touch {
+                    id,
+                    phase,
+                    position,
+                };
+                match self.touches.iter_mut().find(|t| t.id == id) {
+                    Some(existing) => *existing = touch,
+                    None => self.touches.push(touch),
+                }
+                match phase {
+                    TouchPhase::Started => self.touches_started.push(touch),
+                    TouchPhase::Ended | TouchPhase::Cancelled => self.touches_ended.push(touch),
+                    TouchPhase::Moved => (),
+                }
+
+                // Keep emulating the mouse with the first finger, so mouse-only code still works
+                if phase == TouchPhase::Started && self.primary_touch.is_none() {
+                    self.primary_touch = Some(id);
+                }
+                if self.primary_touch == Some(id) {
+                    let emulated = match phase {
+                        TouchPhase::Started => InputEvent::MouseDown(MouseButton::Left, position),
+                        TouchPhase::Moved => InputEvent::MouseMotion(position),
+                        TouchPhase::Ended | TouchPhase::Cancelled => {
+                            self.primary_touch = None;
+                            InputEvent::MouseUp(MouseButton::Left, position)
+                        }
+                    };
+                    self.apply_event(&emulated);
+                }
+            }
+        }
+    }
+
+    /// FPS and frame time statistics, measured from the frame deltas.
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    /// Number of recent frames [`Context::frame_stats`] averages over, 120 by default.
+    pub fn set_frame_stats_window(&mut self, frames: usize) {
+        self.frame_stats.set_window(frames);
+    }
+
+    fn move_mouse(&mut self, position: Vec2) {
+        if let Some(last) = self.mouse_position {
+            self.mouse_delta += position - last;
+        }
+        self.mouse_position = Some(position);
+    }
+
+    fn replay_frame<A: Application>(&mut self, app: &mut A, frame: &RecordedFrame) {
+        self.screen_size = frame.screen_size;
+        for event in frame.events.iter() {
+            self.apply_event(event);
+        }
+        if self.recording.is_some() {
+            self.frame_events.extend(frame.events.iter().cloned());
+        }
+        self.gamepads.set_reports(&frame.gamepads);
+        self.run_frame(app, frame.delta);
+    }
+
+    fn run_frame<A: Application>(&mut self, app: &mut A, delta: f32) {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.frames.push(RecordedFrame {
+                delta,
+                screen_size: self.screen_size,
+                events: std::mem::take(&mut self.frame_events),
+                gamepads: self.gamepads.reports().to_vec(),
+            });
+        }
+
+        self.frame_stats.record(delta);
+        let timestep = self.fixed_timestep;
+        for _ in 0..self.accumulator.advance(&timestep, delta) {
+            app.update(self, timestep.step());
+        }
+        self.interpolation_alpha = self.accumulator.alpha(&timestep);
+
+        app.render(self, delta);
+
+        self.end_frame();
+    }
+
+    fn end_frame(&mut self) {
+        self.mouse_wheel = Vec2::ZERO;
+        self.mouse_delta = Vec2::ZERO;
+        self.raw_mouse_delta = Vec2::ZERO;
+        for state in [&mut self.left, &mut self.right, &mut self.middle] {
+            state.pressed = false;
+            state.released = false;
+            state.double_clicked = false;
+        }
+        for state in self.keys.values_mut() {
+            state.pressed = false;
+            state.released = false;
+            state.repeated = false;
+        }
+        self.text_input.clear();
+        self.touches
+            .retain(|touch| !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled));
+        self.touches_started.clear();
+        self.touches_ended.clear();
     }
 }
 
 pub trait Application {
+    /// Called zero or more times per frame at the rate set with [`Context::set_fixed_timestep`]
+    /// (60 Hz by default), `delta` is always the fixed step. All updates in one frame see the
+    /// same input state.
+    fn update(&mut self, _context: &Context, _delta: f32) {}
+
+    /// Called once per frame after all updates, `delta` is the wall-clock frame time.
+    /// See [`Context::interpolation_alpha`] for smoothing fixed-rate state.
     fn render(&mut self, _context: &Context, _delta: f32) {}
+
+    /// The window was resized, `size` is the new [`Context::screen_size`].
+    fn resized(&mut self, _context: &Context, _size: UVec2) {}
+
+    /// The window was minimized; on mobile the app was sent to the background. Losing focus
+    /// is not reported, miniquad has no focus events.
+    fn suspended(&mut self, _context: &Context) {}
+
+    /// Counterpart of [`Application::suspended`].
+    fn resumed(&mut self, _context: &Context) {}
+
+    /// The user tried to close the window or [`Context::quit`] was called.
+    /// Return `false` to keep running, e.g. to ask about unsaved changes first.
+    fn quit_requested(&mut self, _context: &Context) -> bool {
+        true
+    }
 }
 
 struct Stage<A> {
@@ -135,24 +660,68 @@ struct Stage<A> {
 
 impl<A: Application> EventHandler for Stage<A> {
     fn mouse_motion_event(&mut self, x: f32, y: f32) {
-        self.context.mouse_position = uvec2(x as u32, y as u32);
+        self.context
+            .input_event(InputEvent::MouseMotion(vec2(x, y)));
     }
 
     fn mouse_button_down_event(&mut self, btn: MouseButton, x: f32, y: f32) {
-        self.context.mouse_position = uvec2(x as u32, y as u32);
-        let button_state = self.context.mouse_button_state_mut(btn);
-        button_state.down = true;
-        button_state.pressed = true;
+        self.context
+            .input_event(InputEvent::MouseDown(btn, vec2(x, y)));
     }
 
     fn mouse_button_up_event(&mut self, btn: MouseButton, x: f32, y: f32) {
-        self.context.mouse_position = uvec2(x as u32, y as u32);
-        let button_state = self.context.mouse_button_state_mut(btn);
-        button_state.down = false;
+        self.context
+            .input_event(InputEvent::MouseUp(btn, vec2(x, y)));
     }
 
     fn mouse_wheel_event(&mut self, x: f32, y: f32) {
-        self.context.mouse_wheel = vec2(x, y);
+        self.context.input_event(InputEvent::MouseWheel(vec2(x, y)));
+    }
+
+    fn key_down_event(&mut self, keycode: KeyCode, keymods: KeyMods, repeat: bool) {
+        self.context
+            .input_event(InputEvent::KeyDown(keycode, keymods, repeat));
+    }
+
+    fn char_event(&mut self, character: char, keymods: KeyMods, _repeat: bool) {
+        self.context
+            .input_event(InputEvent::Char(character, keymods));
+    }
+
+    fn key_up_event(&mut self, keycode: KeyCode, keymods: KeyMods) {
+        self.context
+            .input_event(InputEvent::KeyUp(keycode, keymods));
+    }
+
+    fn raw_mouse_motion(&mut self, dx: f32, dy: f32) {
+        self.context
+            .input_event(InputEvent::RawMouseMotion(vec2(dx, dy)));
+    }
+
+    fn touch_event(&mut self, phase: TouchPhase, id: u64, x: f32, y: f32) {
+        self.context
+            .input_event(InputEvent::Touch(phase, id, vec2(x, y)));
+    }
+
+    fn resize_event(&mut self, width: f32, height: f32) {
+        self.context.screen_size = uvec2(width as u32, height as u32);
+        self.app.resized(&self.context, self.context.screen_size);
+    }
+
+    fn window_minimized_event(&mut self) {
+        self.context.suspended = true;
+        self.app.suspended(&self.context);
+    }
+
+    fn window_restored_event(&mut self) {
+        self.context.suspended = false;
+        self.app.resumed(&self.context);
+    }
+
+    fn quit_requested_event(&mut self) {
+        if !self.app.quit_requested(&self.context) {
+            crate::backend::cancel_quit();
+        }
     }
 
     fn draw(&mut self) {
@@ -160,35 +729,81 @@ impl<A: Application> EventHandler for Stage<A> {
     }
 
     fn update(&mut self) {
-        let (w, h) = screen_size();
-        self.context.screen_size = uvec2(w as u32, h as u32);
         let now = date::now();
         let delta = (now - self.last_time) as f32;
         self.last_time = now;
 
-        self.app.render(&self.context, delta);
-
-        self.context.mouse_wheel = Vec2::ZERO;
-        self.context.left.pressed = false;
-        self.context.right.pressed = false;
-        self.context.middle.pressed = false;
+        match self.context.replay.pop_front() {
+            Some(frame) => self.context.replay_frame(&mut self.app, &frame),
+            None => {
+                let (w, h) = screen_size();
+                self.context.screen_size = uvec2(w as u32, h as u32);
+                self.context.gamepads.poll();
+                self.context.run_frame(&mut self.app, delta);
+            }
+        }
     }
 }
 
-pub fn go<A: 'static + Application, F: 'static + FnOnce(&Context) -> A>(app_creator: F) {
-    start(
-        conf::Conf {
-            sample_count: 4,
-            ..conf::Conf::default()
-        },
-        || {
-            let context = Context::default();
-            context.configure_blend();
-            Box::new(Stage {
-                app: app_creator(&context),
-                context,
-                last_time: date::now(),
-            })
-        },
-    );
+pub fn go<A: 'static + Application, F: 'static + FnOnce(&mut Context) -> A>(app_creator: F) {
+    go_with_config(WindowConfig::default(), app_creator);
+}
+
+pub fn go_with_config<A: 'static + Application, F: 'static + FnOnce(&mut Context) -> A>(
+    config: WindowConfig,
+    app_creator: F,
+) {
+    start(config.into_conf(), || {
+        let mut context = Context::with_backend(Rc::new(GlBackend::new()));
+        #[cfg(feature = "gilrs")]
+        match GilrsSource::new() {
+            Ok(source) => context.set_gamepad_source(source),
+            Err(err) => miniquad::warn!("{}", err),
+        }
+        Box::new(Stage {
+            app: app_creator(&mut context),
+            context,
+            last_time: date::now(),
+        })
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mouse_buttons_and_double_click() {
+        let mut ctx = Context::default();
+        ctx.apply_event(&InputEvent::MouseDown(
+            MouseButton::Unknown,
+            vec2(-5.0, 3.0),
+        ));
+        assert!(!ctx.is_mouse_button_down(MouseButton::Unknown));
+        assert_eq!(ctx.mouse_position(), vec2(-5.0, 3.0));
+
+        ctx.apply_event(&InputEvent::MouseDown(MouseButton::Left, vec2(10.0, 10.0)));
+        ctx.apply_event(&InputEvent::MouseUp(MouseButton::Left, vec2(11.0, 10.0)));
+        assert!(ctx.is_mouse_button_pressed(MouseButton::Left));
+        assert!(ctx.is_mouse_button_released(MouseButton::Left));
+        assert!(!ctx.is_mouse_button_double_clicked(MouseButton::Left));
+        assert_eq!(ctx.mouse_delta(), vec2(16.0, 7.0));
+        ctx.end_frame();
+        assert!(!ctx.is_mouse_button_released(MouseButton::Left));
+        assert_eq!(ctx.mouse_delta(), Vec2::ZERO);
+
+        ctx.frame_stats.record(0.2);
+        ctx.apply_event(&InputEvent::MouseDown(MouseButton::Left, vec2(12.0, 11.0)));
+        assert!(ctx.is_mouse_button_double_clicked(MouseButton::Left));
+        ctx.end_frame();
+
+        ctx.frame_stats.record(0.2);
+        ctx.apply_event(&InputEvent::MouseDown(MouseButton::Left, vec2(12.0, 11.0)));
+        assert!(!ctx.is_mouse_button_double_clicked(MouseButton::Left));
+        ctx.end_frame();
+
+        ctx.frame_stats.record(1.0);
+        ctx.apply_event(&InputEvent::MouseDown(MouseButton::Left, vec2(12.0, 11.0)));
+        assert!(!ctx.is_mouse_button_double_clicked(MouseButton::Left));
+    }
 }
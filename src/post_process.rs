@@ -0,0 +1,712 @@
+use crate::backend::as_bytes;
+use crate::math::*;
+use crate::{
+    Backend, BlendMode, BufferId, BufferKind, BufferUsage, Context, DepthMode, DrawCall, OgtError,
+    RenderTarget, RenderTargetBuilder, ShaderId, Uniform, VertexAttribute, VertexFormat,
+    VertexLayout,
+};
+use std::rc::Rc;
+
+/// Screen-wide effect, see [`PostProcess`]. Colors are in the range 0 to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    /// Mixes towards the luminance, `amount` 1 is fully gray.
+    Grayscale { amount: f32 },
+    /// Darkens the corners. Starts at `radius` from the center (0.5 reaches the edges) and
+    /// fades in over `softness`.
+    Vignette {
+        strength: f32,
+        radius: f32,
+        softness: f32,
+    },
+    /// Gaussian blur, `radius` in pixels.
+    Blur { radius: f32 },
+    /// Blurs everything brighter than `threshold` and adds it on top of the output of the
+    /// effects before it.
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        radius: f32,
+    },
+    /// Curved screen with `scanlines` dark lines over the height.
+    Crt {
+        curvature: f32,
+        scanlines: f32,
+        strength: f32,
+    },
+    /// Applied in this order: brightness is added, contrast scales around 0.5, saturation
+    /// mixes with the luminance and the result is multiplied with `tint`.
+    ColorGrading {
+        brightness: f32,
+        contrast: f32,
+        saturation: f32,
+        tint: Vec3,
+    },
+    /// A fragment shader of your own, `uniforms` are set once, see
+    /// [`PostProcess::set_uniform`] for changing them later. It gets these inputs:
+    /// ```glsl
+    /// #version 100
+    /// precision mediump float;
+    /// varying vec2 uv;
+    /// // Output of the previous pass
+    /// uniform sampler2D source;
+    /// // The frame before any effect
+    /// uniform sampler2D scene;
+    /// // Size of the output in pixels
+    /// uniform vec2 resolution;
+    /// ```
+    Custom {
+        fragment_shader: String,
+        uniforms: Vec<(String, Uniform)>,
+    },
+}
+
+impl Effect {
+    /// Fragment shader and uniforms of each pass.
+    fn passes(&self) -> Vec<(String, Vec<(String, Uniform)>)> {
+        let builtin = |body: &str, uniforms: &[(&str, Uniform)]| {
+            (
+                format!("{HEADER}{body}"),
+                uniforms
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), *value))
+                    .collect(),
+            )
+        };
+        match *self {
+            Effect::Grayscale { amount } => {
+                vec![builtin(GRAYSCALE, &[("amount", Uniform::Float(amount))])]
+            }
+            Effect::Vignette {
+                strength,
+                radius,
+                softness,
+            } => vec![builtin(
+                VIGNETTE,
+                &[
+                    ("strength", Uniform::Float(strength)),
+                    ("radius", Uniform::Float(radius)),
+                    ("softness", Uniform::Float(softness)),
+                ],
+            )],
+            Effect::Blur { radius } => vec![
+                builtin(
+                    BLUR,
+                    &[
+                        ("radius", Uniform::Float(radius)),
+                        ("direction", Uniform::Vec2(Vec2::X)),
+                    ],
+                ),
+                builtin(
+                    BLUR,
+                    &[
+                        ("radius", Uniform::Float(radius)),
+                        ("direction", Uniform::Vec2(Vec2::Y)),
+                    ],
+                ),
+            ],
+            Effect::Bloom {
+                threshold,
+                intensity,
+                radius,
+            } => {
+                let mut passes = vec![builtin(
+                    BRIGHT_PASS,
+                    &[("threshold", Uniform::Float(threshold))],
+                )];
+                passes.extend(Effect::Blur { radius }.passes());
+                passes.push(builtin(
+                    BLOOM_COMBINE,
+                    &[("intensity", Uniform::Float(intensity))],
+                ));
+                passes
+            }
+            Effect::Crt {
+                curvature,
+                scanlines,
+                strength,
+            } => vec![builtin(
+                CRT,
+                &[
+                    ("curvature", Uniform::Float(curvature)),
+                    ("scanlines", Uniform::Float(scanlines)),
+                    ("strength", Uniform::Float(strength)),
+                ],
+            )],
+            Effect::ColorGrading {
+                brightness,
+                contrast,
+                saturation,
+                tint,
+            } => vec![builtin(
+                COLOR_GRADING,
+                &[
+                    ("brightness", Uniform::Float(brightness)),
+                    ("contrast", Uniform::Float(contrast)),
+                    ("saturation", Uniform::Float(saturation)),
+                    ("tint", Uniform::Vec3(tint)),
+                ],
+            )],
+            Effect::Custom {
+                ref fragment_shader,
+                ref uniforms,
+            } => vec![(fragment_shader.clone(), uniforms.clone())],
+        }
+    }
+}
+
+struct Pass {
+    shader: ShaderId,
+    uniforms: Vec<(String, Uniform)>,
+}
+
+/// Runs fullscreen fragment shader passes over the rendered frame. Everything drawn between
+/// [`PostProcess::begin`] and [`PostProcess::end`] goes to an offscreen [`RenderTarget`],
+/// `end` runs the effects in the order they were added and writes the result to whatever
+/// was bound before `begin`, usually the screen.
+///
+/// ```no_run
+/// # use ogt::*;
+/// # let ctx = Context::default();
+/// let mut post = PostProcess::new(&ctx);
+/// let vignette = post.add(&ctx, Effect::Vignette { strength: 0.8, radius: 0.5, softness: 0.3 });
+/// post.add(&ctx, Effect::Grayscale { amount: 1.0 });
+/// // Every frame
+/// post.begin(&ctx)?;
+/// // ... draw the scene
+/// post.end(&ctx);
+/// post.set_uniform(vignette, "strength", Uniform::Float(0.5));
+/// # Ok::<(), OgtError>(())
+/// ```
+///
+/// The [`crate::SoftwareBackend`] doesn't execute shaders, each pass just copies its input.
+pub struct PostProcess {
+    backend: Rc<dyn Backend>,
+    vertices: BufferId,
+    layout: VertexLayout,
+    copy: ShaderId,
+    effects: Vec<Vec<Pass>>,
+    /// The frame and three buffers for the passes, all of the screen size. Three so the input
+    /// of an effect survives its passes, Bloom adds to it in the end.
+    targets: Vec<RenderTarget>,
+    /// What was bound before [`PostProcess::begin`]
+    output: Option<RenderTarget>,
+    /// Between [`PostProcess::begin`] and [`PostProcess::end`]
+    active: bool,
+}
+
+impl PostProcess {
+    pub fn new(context: &Context) -> Self {
+        Self::try_new(context).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_new(context: &Context) -> Result<Self, OgtError> {
+        let backend = context.backend().clone();
+        let layout = VertexLayout {
+            stride: 16,
+            attributes: vec![
+                VertexAttribute {
+                    name: "position",
+                    format: VertexFormat::Float2,
+                    offset: 0,
+                },
+                VertexAttribute {
+                    name: "tex_uv",
+                    format: VertexFormat::Float2,
+                    offset: 8,
+                },
+            ],
+            ..Default::default()
+        };
+        let copy = backend.create_shader(VERTEX_SHADER, &format!("{HEADER}{COPY}"), &layout)?;
+        let vertices = backend.create_buffer(BufferKind::Vertex, BufferUsage::Static);
+        #[rustfmt::skip]
+        let quad: [f32; 24] = [
+            -1.0, -1.0, 0.0, 0.0,
+            1.0, -1.0, 1.0, 0.0,
+            1.0, 1.0, 1.0, 1.0,
+            -1.0, -1.0, 0.0, 0.0,
+            1.0, 1.0, 1.0, 1.0,
+            -1.0, 1.0, 0.0, 1.0,
+        ];
+        backend.update_buffer(vertices, as_bytes(&quad));
+        Ok(Self {
+            backend,
+            vertices,
+            layout,
+            copy,
+            effects: Vec::new(),
+            targets: Vec::new(),
+            output: None,
+            active: false,
+        })
+    }
+
+    /// Appends `effect` to the chain and returns its index for [`PostProcess::set_uniform`].
+    /// Panics if a shader doesn't compile, see [`PostProcess::try_add`].
+    pub fn add(&mut self, context: &Context, effect: Effect) -> usize {
+        self.try_add(context, effect)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_add(&mut self, _context: &Context, effect: Effect) -> Result<usize, OgtError> {
+        let mut passes = Vec::new();
+        for (fragment_shader, uniforms) in effect.passes() {
+            match self
+                .backend
+                .create_shader(VERTEX_SHADER, &fragment_shader, &self.layout)
+            {
+                Ok(shader) => passes.push(Pass { shader, uniforms }),
+                Err(err) => {
+                    for pass in passes {
+                        self.backend.delete_shader(pass.shader);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        self.effects.push(passes);
+        Ok(self.effects.len() - 1)
+    }
+
+    /// Removes all effects, the frame is copied unchanged afterwards.
+    pub fn clear(&mut self) {
+        for pass in self.effects.drain(..).flatten() {
+            self.backend.delete_shader(pass.shader);
+        }
+    }
+
+    /// Changes a uniform of every pass of the effect at index `effect`.
+    pub fn set_uniform(&mut self, effect: usize, name: &str, value: Uniform) {
+        for pass in self.effects[effect].iter_mut() {
+            match pass
+                .uniforms
+                .iter_mut()
+                .find(|(existing, _)| existing == name)
+            {
+                Some((_, existing)) => *existing = value,
+                None => pass.uniforms.push((name.to_string(), value)),
+            }
+        }
+    }
+
+    /// Redirects drawing to the offscreen frame, which is resized to the screen if needed.
+    /// The frame has a depth buffer for depth tested sprites. Fails if the offscreen targets
+    /// can't be created, panics if called twice without [`PostProcess::end`].
+    pub fn begin(&mut self, context: &Context) -> Result<(), OgtError> {
+        assert!(
+            !self.active,
+            "PostProcess::begin called twice without PostProcess::end"
+        );
+        let size = context.screen_size().max(UVec2::ONE);
+        if self.targets.first().map(RenderTarget::size) != Some(size) {
+            self.targets.clear();
+            self.targets = (0..4)
+                .map(|i| {
+                    RenderTargetBuilder::new(size.x, size.y)
+                        .depth_stencil(i == 0)
+                        .try_build(context)
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        self.active = true;
+        self.output = context.render_target();
+        context.set_render_target(Some(&self.targets[0]));
+        Ok(())
+    }
+
+    /// Runs the effects and draws the result to what was bound before [`PostProcess::begin`].
+    /// Panics if `begin` wasn't called.
+    pub fn end(&mut self, context: &Context) {
+        assert!(
+            self.active,
+            "PostProcess::end called without PostProcess::begin"
+        );
+        self.active = false;
+        let output = self.output.take();
+        let copy = vec![Pass {
+            shader: self.copy,
+            uniforms: Vec::new(),
+        }];
+        let effects = if self.effects.is_empty() {
+            std::slice::from_ref(&copy)
+        } else {
+            &self.effects[..]
+        };
+        let mut remaining: usize = effects.iter().map(Vec::len).sum();
+        let scene = self.targets[0].texture().id();
+        let mut source = scene;
+        for passes in effects {
+            // Input of the effect, kept until its last pass
+            let base = source;
+            for pass in passes {
+                remaining -= 1;
+                let destination = if remaining == 0 {
+                    output.as_ref()
+                } else {
+                    self.targets[1..].iter().find(|target| {
+                        let texture = target.texture().id();
+                        texture != source && texture != base
+                    })
+                };
+                context.set_render_target(destination);
+                let resolution = destination
+                    .map_or(context.screen_size(), RenderTarget::size)
+                    .as_vec2();
+                let mut uniforms = vec![
+                    ("source", Uniform::Int(0)),
+                    ("scene", Uniform::Int(1)),
+                    ("base", Uniform::Int(2)),
+                    ("resolution", Uniform::Vec2(resolution)),
+                ];
+                uniforms.extend(
+                    pass.uniforms
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), *value)),
+                );
+                self.backend.draw(&DrawCall {
+                    shader: pass.shader,
+                    vertices: self.vertices,
+                    indices: None,
+                    count: 6,
+                    instances: None,
+                    textures: &[source, scene, base],
+                    uniforms: &uniforms,
+                    // The effects decide about alpha themselves
+                    blend: BlendMode::Replace,
+                    depth: DepthMode::Disabled,
+                });
+                if let Some(destination) = destination {
+                    source = destination.texture().id();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for PostProcess {
+    fn drop(&mut self) {
+        self.clear();
+        self.backend.delete_shader(self.copy);
+        self.backend.delete_buffer(self.vertices);
+    }
+}
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec2 position;
+attribute vec2 tex_uv;
+
+varying vec2 uv;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+    uv = tex_uv;
+}
+"#;
+
+const HEADER: &str = r#"#version 100
+precision mediump float;
+varying vec2 uv;
+uniform sampler2D source;
+uniform sampler2D scene;
+uniform vec2 resolution;
+
+float luminance(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+"#;
+
+const COPY: &str = r#"
+void main() {
+    gl_FragColor = vec4(texture2D(source, uv).rgb, 1.0);
+}
+"#;
+
+const GRAYSCALE: &str = r#"
+uniform float amount;
+
+void main() {
+    vec3 color = texture2D(source, uv).rgb;
+    gl_FragColor = vec4(mix(color, vec3(luminance(color)), amount), 1.0);
+}
+"#;
+
+const VIGNETTE: &str = r#"
+uniform float strength;
+uniform float radius;
+uniform float softness;
+
+void main() {
+    vec3 color = texture2D(source, uv).rgb;
+    float vignette = smoothstep(radius, radius + softness, distance(uv, vec2(0.5)));
+    gl_FragColor = vec4(color * (1.0 - vignette * strength), 1.0);
+}
+"#;
+
+const BLUR: &str = r#"
+uniform float radius;
+uniform vec2 direction;
+
+void main() {
+    // 9 tap gaussian, spread over the radius
+    vec2 step = direction / resolution * radius / 4.0;
+    vec3 color = texture2D(source, uv).rgb * 0.227027;
+    color += texture2D(source, uv + step).rgb * 0.1945946;
+    color += texture2D(source, uv - step).rgb * 0.1945946;
+    color += texture2D(source, uv + step * 2.0).rgb * 0.1216216;
+    color += texture2D(source, uv - step * 2.0).rgb * 0.1216216;
+    color += texture2D(source, uv + step * 3.0).rgb * 0.054054;
+    color += texture2D(source, uv - step * 3.0).rgb * 0.054054;
+    color += texture2D(source, uv + step * 4.0).rgb * 0.016216;
+    color += texture2D(source, uv - step * 4.0).rgb * 0.016216;
+    gl_FragColor = vec4(color, 1.0);
+}
+"#;
+
+const BRIGHT_PASS: &str = r#"
+uniform float threshold;
+
+void main() {
+    vec3 color = texture2D(source, uv).rgb;
+    gl_FragColor = vec4(color * smoothstep(threshold, threshold + 0.1, luminance(color)), 1.0);
+}
+"#;
+
+const BLOOM_COMBINE: &str = r#"
+// Input of the bloom effect, the output of the effects before it
+uniform sampler2D base;
+uniform float intensity;
+
+void main() {
+    vec3 color = texture2D(base, uv).rgb + texture2D(source, uv).rgb * intensity;
+    gl_FragColor = vec4(color, 1.0);
+}
+"#;
+
+const CRT: &str = r#"
+uniform float curvature;
+uniform float scanlines;
+uniform float strength;
+
+void main() {
+    vec2 centered = uv * 2.0 - 1.0;
+    centered *= 1.0 + curvature * dot(centered.yx, centered.yx);
+    vec2 curved = centered * 0.5 + 0.5;
+    if (curved.x < 0.0 || curved.x > 1.0 || curved.y < 0.0 || curved.y > 1.0) {
+        gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    vec3 color = texture2D(source, curved).rgb;
+    float scanline = 0.5 + 0.5 * sin(curved.y * scanlines * 6.2831853);
+    gl_FragColor = vec4(color * mix(1.0, scanline, strength), 1.0);
+}
+"#;
+
+const COLOR_GRADING: &str = r#"
+uniform float brightness;
+uniform float contrast;
+uniform float saturation;
+uniform vec3 tint;
+
+void main() {
+    vec3 color = texture2D(source, uv).rgb + brightness;
+    color = (color - 0.5) * contrast + 0.5;
+    color = mix(vec3(luminance(color)), color, saturation);
+    gl_FragColor = vec4(clamp(color * tint, 0.0, 1.0), 1.0);
+}
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ShapeBatch, SoftwareBackend, SpriteBatch, TextureBuilder, BLACK, BLUE, RED};
+
+    fn scene(context: &Context) {
+        context.clear_screen(BLACK);
+        let mut shapes = ShapeBatch::new(context);
+        shapes.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+            0.0, 8.0, 4.0, 0.0, -1.0, 1.0,
+        ));
+        shapes.add_filled_rect(context, vec2(0.0, 0.0), vec2(4.0, 2.0), RED);
+        shapes.draw(context);
+    }
+
+    #[test]
+    fn passes_keep_the_frame_in_place() {
+        let image = SoftwareBackend::render(8, 4, |ctx| {
+            let mut post = PostProcess::new(ctx);
+            post.add(ctx, Effect::Blur { radius: 2.0 });
+            let grayscale = post.add(ctx, Effect::Grayscale { amount: 1.0 });
+            post.set_uniform(grayscale, "amount", Uniform::Float(0.5));
+            post.begin(ctx).unwrap();
+            scene(ctx);
+            post.end(ctx);
+            assert!(ctx.render_target().is_none());
+        });
+        assert_eq!(image.get_pixel(0, 0).0, RED.0);
+        assert_eq!(image.get_pixel(3, 1).0, RED.0);
+        assert_eq!(image.get_pixel(4, 1).0, BLACK.0);
+        assert_eq!(image.get_pixel(0, 2).0, BLACK.0);
+    }
+
+    #[test]
+    fn frame_has_a_depth_buffer() {
+        let image = SoftwareBackend::render(8, 4, |ctx| {
+            let mut post = PostProcess::new(ctx);
+            post.begin(ctx).unwrap();
+            ctx.clear_screen(BLACK);
+            let texture = TextureBuilder::from_bytes(&[255; 4], 1, 1).build(ctx);
+            let mut sprites = SpriteBatch::new(ctx);
+            sprites.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+                0.0, 8.0, 0.0, 4.0, -1.0, 1.0,
+            ));
+            sprites.set_depth_test(ctx, true);
+            let region = texture.as_region();
+            // Blue is submitted last but behind red
+            for (color, z) in [(RED, 0.5), (BLUE, 0.0)] {
+                sprites.add(ctx, &texture, region, color, Vec2::ZERO, vec3(0.0, 0.0, z));
+            }
+            sprites.draw(ctx);
+            post.end(ctx);
+        });
+        assert_eq!(image.get_pixel(0, 3).0, RED.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "twice without PostProcess::end")]
+    fn begin_needs_end() {
+        SoftwareBackend::render(8, 4, |ctx| {
+            let mut post = PostProcess::new(ctx);
+            post.begin(ctx).unwrap();
+            post.begin(ctx).unwrap();
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "without PostProcess::begin")]
+    fn end_needs_begin() {
+        SoftwareBackend::render(8, 4, |ctx| {
+            let mut post = PostProcess::new(ctx);
+            post.end(ctx);
+        });
+    }
+
+    /// Renders the scene through `effects`, `None` if GL tests are skipped.
+    #[cfg(feature = "headless")]
+    fn render_on_gl(effects: Vec<Effect>) -> Option<image::RgbaImage> {
+        let mut headless = crate::Headless::for_gl_test(8, 4)?;
+        let ctx = headless.context();
+        let mut post = PostProcess::new(ctx);
+        for effect in effects {
+            post.add(ctx, effect);
+        }
+        post.begin(ctx).unwrap();
+        scene(ctx);
+        post.end(ctx);
+        drop(post);
+        Some(headless.read_pixels())
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn effects_run_in_order_on_gl() {
+        let grayscale = Effect::Grayscale { amount: 1.0 };
+        let red = Effect::ColorGrading {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            tint: vec3(1.0, 0.0, 0.0),
+        };
+        let Some(image) = render_on_gl(vec![red.clone(), grayscale.clone()]) else {
+            return;
+        };
+        let [r, g, b, _] = image.get_pixel(0, 0).0;
+        assert!(r == g && g == b && r > 50, "{:?}", image.get_pixel(0, 0));
+
+        let image = render_on_gl(vec![grayscale, red]).unwrap();
+        let [r, g, b, _] = image.get_pixel(0, 0).0;
+        assert!(r > 50 && g == 0 && b == 0, "{:?}", image.get_pixel(0, 0));
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn bloom_keeps_previous_effects_on_gl() {
+        let Some(grayscale) = render_on_gl(vec![Effect::Grayscale { amount: 1.0 }]) else {
+            return;
+        };
+        let image = render_on_gl(vec![
+            Effect::Grayscale { amount: 1.0 },
+            Effect::Bloom {
+                threshold: 0.1,
+                intensity: 1.0,
+                radius: 2.0,
+            },
+        ])
+        .unwrap();
+        for (x, y) in [(0, 0), (3, 1), (4, 1)] {
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            assert!(r == g && g == b, "{x},{y}: {:?}", image.get_pixel(x, y));
+        }
+        // Brighter than without the bloom, including the glow next to the rect
+        assert!(image.get_pixel(0, 0).0[0] > grayscale.get_pixel(0, 0).0[0]);
+        assert!(image.get_pixel(4, 1).0[0] > 0);
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn effects_run_on_gl() {
+        let Some(mut headless) = crate::Headless::for_gl_test(8, 4) else {
+            return;
+        };
+        let ctx = headless.context();
+        let mut all = PostProcess::new(ctx);
+        for effect in [
+            Effect::Vignette {
+                strength: 1.0,
+                radius: 0.4,
+                softness: 0.2,
+            },
+            Effect::Blur { radius: 2.0 },
+            Effect::Bloom {
+                threshold: 0.8,
+                intensity: 1.0,
+                radius: 4.0,
+            },
+            Effect::Crt {
+                curvature: 0.1,
+                scanlines: 120.0,
+                strength: 0.5,
+            },
+            Effect::ColorGrading {
+                brightness: 0.1,
+                contrast: 1.2,
+                saturation: 0.8,
+                tint: vec3(1.0, 0.9, 0.8),
+            },
+        ] {
+            all.try_add(ctx, effect).unwrap();
+        }
+        drop(all);
+
+        let mut post = PostProcess::new(ctx);
+        post.add(ctx, Effect::Grayscale { amount: 1.0 });
+        assert!(post
+            .try_add(
+                ctx,
+                Effect::Custom {
+                    fragment_shader: "not glsl".to_string(),
+                    uniforms: Vec::new(),
+                },
+            )
+            .is_err());
+        post.begin(ctx).unwrap();
+        scene(ctx);
+        post.end(ctx);
+
+        let image = headless.read_pixels();
+        let [r, g, b, _] = image.get_pixel(0, 0).0;
+        assert!(r == g && g == b && r > 50, "{:?}", image.get_pixel(0, 0));
+        assert_eq!(image.get_pixel(4, 1).0, BLACK.0);
+        assert_eq!(image.get_pixel(0, 2).0, BLACK.0);
+    }
+}
@@ -64,8 +64,7 @@ fn pack_limited(
             .iter()
             .enumerate()
             .rev()
-            .filter(|(_, s)| s.width >= update.width && s.height >= update.height)
-            .next()
+            .find(|(_, s)| s.width >= update.width && s.height >= update.height)
         {
             update.x = space.x;
             update.y = space.y;
@@ -122,8 +121,11 @@ fn pack_limited(
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(feature = "debug_images")]
     use image::*;
+    #[cfg(feature = "debug_images")]
     use imageproc::drawing::*;
+    #[cfg(feature = "debug_images")]
     use imageproc::rect::Rect as r;
     use quad_rand::*;
 
@@ -148,6 +150,7 @@ mod test {
 
         #[cfg(feature = "debug_images")]
         {
+            let dim = dim.unwrap();
             let mut image = RgbImage::new(dim.0, dim.1);
             for rect in rects.iter() {
                 draw_filled_rect_mut(
@@ -161,8 +164,9 @@ mod test {
                 &image.into_raw(),
                 dim.0,
                 dim.1,
-                ColorType::Rgb8,
-            );
+                ExtendedColorType::Rgb8,
+            )
+            .unwrap();
         }
     }
 }
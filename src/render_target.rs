@@ -0,0 +1,136 @@
+use crate::math::*;
+use crate::{Backend, Context, OgtError, RenderTargetId, Texture, TextureFilter};
+use std::rc::Rc;
+
+struct RenderTargetHandle {
+    id: RenderTargetId,
+    backend: Rc<dyn Backend>,
+}
+
+impl Drop for RenderTargetHandle {
+    fn drop(&mut self) {
+        self.backend.delete_render_target(self.id);
+    }
+}
+
+/// Offscreen framebuffer backed by a [`Texture`]. Select it with
+/// [`Context::set_render_target`], afterwards the texture can be drawn like any other, e.g.
+/// for minimaps, pixel art upscaling or cached UI panels.
+#[derive(Clone)]
+pub struct RenderTarget {
+    handle: Rc<RenderTargetHandle>,
+    texture: Texture,
+}
+
+impl RenderTarget {
+    pub fn id(&self) -> RenderTargetId {
+        self.handle.id
+    }
+
+    /// The rendered content. Rows are stored bottom first, see [`Texture::is_flipped`].
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn size(&self) -> UVec2 {
+        uvec2(self.texture.width, self.texture.height)
+    }
+}
+
+impl PartialEq for RenderTarget {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle.id == other.handle.id
+    }
+}
+
+pub struct RenderTargetBuilder {
+    width: u32,
+    height: u32,
+    filter: TextureFilter,
+    depth_stencil: bool,
+}
+
+impl RenderTargetBuilder {
+    pub fn new(width: u32, height: u32) -> Self {
+        RenderTargetBuilder {
+            width,
+            height,
+            filter: TextureFilter::Linear,
+            depth_stencil: false,
+        }
+    }
+
+    /// Filter of the texture, `Nearest` for pixel art upscaling.
+    pub fn filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Adds a depth and stencil buffer.
+    pub fn depth_stencil(mut self, depth_stencil: bool) -> Self {
+        self.depth_stencil = depth_stencil;
+        self
+    }
+
+    /// Panics if the backend can't create the framebuffer, see [`RenderTargetBuilder::try_build`].
+    pub fn build(self, context: &Context) -> RenderTarget {
+        self.try_build(context)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_build(self, context: &Context) -> Result<RenderTarget, OgtError> {
+        let backend = context.backend().clone();
+        let texture_id = backend.create_texture(self.width, self.height, &[], self.filter);
+        let texture = Texture::new(backend.clone(), texture_id, self.width, self.height, true);
+        let id = backend.create_render_target(
+            texture_id,
+            self.width,
+            self.height,
+            self.depth_stencil,
+        )?;
+        Ok(RenderTarget {
+            handle: Rc::new(RenderTargetHandle { id, backend }),
+            texture,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ShapeBatch, SoftwareBackend, SpriteBatch, BLACK, RED, WHITE};
+
+    #[test]
+    fn render_target_is_drawn_upright() {
+        let image = SoftwareBackend::render(8, 8, |ctx| {
+            let target = RenderTargetBuilder::new(4, 4)
+                .filter(TextureFilter::Nearest)
+                .build(ctx);
+            ctx.set_render_target(Some(&target));
+            ctx.clear_screen(BLACK);
+            let mut shapes = ShapeBatch::new(ctx);
+            shapes.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+                0.0, 4.0, 4.0, 0.0, -1.0, 1.0,
+            ));
+            // Top left quarter
+            shapes.add_filled_rect(ctx, vec2(0.0, 0.0), vec2(2.0, 2.0), RED);
+            shapes.draw(ctx);
+            ctx.set_render_target(None);
+
+            ctx.clear_screen(WHITE);
+            let mut sprites = SpriteBatch::new(ctx);
+            // Sprites expect y to point up, like text
+            sprites.set_model_view_projection_matrix(
+                Mat4::orthographic_rh_gl(0.0, 8.0, 0.0, 8.0, -1.0, 1.0)
+                    * Mat4::from_scale(vec3(2.0, 2.0, 1.0)),
+            );
+            let region = target.texture().as_region();
+            sprites.add(ctx, target.texture(), region, WHITE, Vec2::ZERO, Vec2::ZERO);
+            sprites.draw(ctx);
+        });
+        assert_eq!(image.get_pixel(0, 0).0, RED.0);
+        assert_eq!(image.get_pixel(3, 3).0, RED.0);
+        assert_eq!(image.get_pixel(4, 3).0, BLACK.0);
+        assert_eq!(image.get_pixel(7, 7).0, BLACK.0);
+    }
+}
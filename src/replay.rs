@@ -0,0 +1,557 @@
+use crate::input::KEY_CODES;
+use crate::math::*;
+use crate::{
+    Application, Context, GamepadAxis, GamepadButton, GamepadReport, InputEvent, KeyCode, KeyMods,
+    MouseButton, OgtError, Touch, TouchPhase,
+};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"OGTR";
+const VERSION: u8 = 2;
+
+/// Everything that was fed into the [`Context`] for one frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedFrame {
+    pub delta: f32,
+    pub screen_size: UVec2,
+    pub events: Vec<InputEvent>,
+    pub gamepads: Vec<GamepadReport>,
+}
+
+/// What was held when a recording started, the events of the first frame build on it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct InputState {
+    pub(crate) mouse_position: Option<Vec2>,
+    pub(crate) mouse_buttons: Vec<MouseButton>,
+    pub(crate) keys: Vec<KeyCode>,
+    pub(crate) key_mods: KeyMods,
+    pub(crate) touches: Vec<Touch>,
+    pub(crate) primary_touch: Option<u64>,
+    pub(crate) gamepads: Vec<GamepadReport>,
+}
+
+/// Per-frame input stream captured with [`Context::start_recording`]. Replay it with
+/// [`Context::start_replay`] in a running game, or [`replay`] to drive an [`Application`] directly.
+/// Input held when the recording started is part of it and restored before replaying.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    pub(crate) initial: InputState,
+    pub(crate) frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    pub fn push_frame(&mut self, frame: RecordedFrame) {
+        self.frames.push(frame);
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, OgtError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer(MAGIC.to_vec());
+        out.u8(VERSION);
+        out.input_state(&self.initial);
+        out.varint(self.frames.len() as u64);
+        for frame in self.frames.iter() {
+            out.f32(frame.delta);
+            out.varint(frame.screen_size.x as u64);
+            out.varint(frame.screen_size.y as u64);
+            out.varint(frame.events.len() as u64);
+            for event in frame.events.iter() {
+                out.event(event);
+            }
+            out.gamepads(&frame.gamepads);
+        }
+        out.0
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OgtError> {
+        Self::parse(bytes).map_err(OgtError::InvalidData)
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut input = Reader(bytes);
+        if input.take(4)? != MAGIC {
+            return Err("Not an input recording".to_string());
+        }
+        let version = input.u8()?;
+        if version == 0 || version > VERSION {
+            return Err(format!("Unsupported recording version {}", version));
+        }
+        // Version 1 started without anything held
+        let initial = match version {
+            1 => InputState::default(),
+            _ => input.input_state()?,
+        };
+        let frame_count = input.varint()?;
+        let mut frames = Vec::new();
+        for _ in 0..frame_count {
+            let delta = input.f32()?;
+            let screen_size = uvec2(input.varint()? as u32, input.varint()? as u32);
+            let event_count = input.varint()?;
+            let events = (0..event_count)
+                .map(|_| input.event())
+                .collect::<Result<_, _>>()?;
+            let gamepads = input.gamepads()?;
+            frames.push(RecordedFrame {
+                delta,
+                screen_size,
+                events,
+                gamepads,
+            });
+        }
+        if !input.0.is_empty() {
+            return Err("Trailing data after recording".to_string());
+        }
+        Ok(Self { initial, frames })
+    }
+}
+
+/// Runs `app` frame by frame with the input and frame times of `recording`. This calls
+/// [`Application::render`] as well, so a rendering context must be available.
+pub fn replay<A: Application>(recording: &Recording, context: &mut Context, app: &mut A) {
+    context.restore_input(&recording.initial);
+    for frame in recording.frames.iter() {
+        context.replay_frame(app, frame);
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+
+    /// LEB128, most values are small
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn mods(&mut self, mods: KeyMods, repeat: bool) {
+        self.u8(mods.shift as u8
+            | (mods.ctrl as u8) << 1
+            | (mods.alt as u8) << 2
+            | (mods.logo as u8) << 3
+            | (repeat as u8) << 4);
+    }
+
+    fn event(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::MouseMotion(position) => {
+                self.u8(0);
+                self.vec2(position);
+            }
+            InputEvent::MouseDown(button, position) => {
+                self.u8(1);
+                self.u8(button as u8);
+                self.vec2(position);
+            }
+            InputEvent::MouseUp(button, position) => {
+                self.u8(2);
+                self.u8(button as u8);
+                self.vec2(position);
+            }
+            InputEvent::MouseWheel(wheel) => {
+                self.u8(3);
+                self.vec2(wheel);
+            }
+            InputEvent::KeyDown(key, mods, repeat) => {
+                self.u8(4);
+                self.varint(key as u64);
+                self.mods(mods, repeat);
+            }
+            InputEvent::KeyUp(key, mods) => {
+                self.u8(5);
+                self.varint(key as u64);
+                self.mods(mods, false);
+            }
+            InputEvent::Char(c, mods) => {
+                self.u8(6);
+                self.varint(c as u64);
+                self.mods(mods, false);
+            }
+            InputEvent::Touch(phase, id, position) => {
+                self.u8(7);
+                self.u8(phase as u8);
+                self.varint(id);
+                self.vec2(position);
+            }
+            InputEvent::RawMouseMotion(delta) => {
+                self.u8(8);
+                self.vec2(delta);
+            }
+        }
+    }
+
+    fn gamepads(&mut self, pads: &[GamepadReport]) {
+        self.varint(pads.len() as u64);
+        for pad in pads.iter() {
+            self.varint(pad.id as u64);
+            let buttons = pad
+                .buttons
+                .iter()
+                .enumerate()
+                .fold(0, |mask, (i, &down)| mask | (down as u64) << i);
+            self.varint(buttons);
+            for &axis in pad.axes.iter() {
+                self.f32(axis);
+            }
+        }
+    }
+
+    fn input_state(&mut self, state: &InputState) {
+        self.u8(state.mouse_position.is_some() as u8);
+        self.vec2(state.mouse_position.unwrap_or_default());
+        self.varint(state.mouse_buttons.len() as u64);
+        for &button in state.mouse_buttons.iter() {
+            self.u8(button as u8);
+        }
+        self.varint(state.keys.len() as u64);
+        for &key in state.keys.iter() {
+            self.varint(key as u64);
+        }
+        self.mods(state.key_mods, false);
+        self.varint(state.touches.len() as u64);
+        for touch in state.touches.iter() {
+            self.event(&InputEvent::Touch(touch.phase, touch.id, touch.position));
+        }
+        self.u8(state.primary_touch.is_some() as u8);
+        self.varint(state.primary_touch.unwrap_or_default());
+        self.gamepads(&state.gamepads);
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], String> {
+        if self.0.len() < count {
+            return Err("Unexpected end of recording".to_string());
+        }
+        let (head, tail) = self.0.split_at(count);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn vec2(&mut self) -> Result<Vec2, String> {
+        Ok(vec2(self.f32()?, self.f32()?))
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Invalid varint in recording".to_string())
+    }
+
+    fn mods(&mut self) -> Result<(KeyMods, bool), String> {
+        let bits = self.u8()?;
+        let mods = KeyMods {
+            shift: bits & 1 != 0,
+            ctrl: bits & 1 << 1 != 0,
+            alt: bits & 1 << 2 != 0,
+            logo: bits & 1 << 3 != 0,
+        };
+        Ok((mods, bits & 1 << 4 != 0))
+    }
+
+    fn button(&mut self) -> Result<MouseButton, String> {
+        Ok(match self.u8()? {
+            0 => MouseButton::Left,
+            1 => MouseButton::Middle,
+            2 => MouseButton::Right,
+            _ => MouseButton::Unknown,
+        })
+    }
+
+    fn key(&mut self) -> Result<KeyCode, String> {
+        let code = self.varint()?;
+        Ok(KEY_CODES
+            .iter()
+            .copied()
+            .find(|&key| key as u64 == code)
+            .unwrap_or(KeyCode::Unknown))
+    }
+
+    fn event(&mut self) -> Result<InputEvent, String> {
+        Ok(match self.u8()? {
+            0 => InputEvent::MouseMotion(self.vec2()?),
+            1 => InputEvent::MouseDown(self.button()?, self.vec2()?),
+            2 => InputEvent::MouseUp(self.button()?, self.vec2()?),
+            3 => InputEvent::MouseWheel(self.vec2()?),
+            4 => {
+                let key = self.key()?;
+                let (mods, repeat) = self.mods()?;
+                InputEvent::KeyDown(key, mods, repeat)
+            }
+            5 => InputEvent::KeyUp(self.key()?, self.mods()?.0),
+            6 => {
+                let c = char::from_u32(self.varint()? as u32)
+                    .ok_or_else(|| "Invalid character in recording".to_string())?;
+                InputEvent::Char(c, self.mods()?.0)
+            }
+            7 => {
+                let phase = match self.u8()? {
+                    0 => TouchPhase::Started,
+                    1 => TouchPhase::Moved,
+                    2 => TouchPhase::Ended,
+                    _ => TouchPhase::Cancelled,
+                };
+                InputEvent::Touch(phase, self.varint()?, self.vec2()?)
+            }
+            8 => InputEvent::RawMouseMotion(self.vec2()?),
+            tag => return Err(format!("Unknown event type {} in recording", tag)),
+        })
+    }
+
+    fn gamepads(&mut self) -> Result<Vec<GamepadReport>, String> {
+        let pad_count = self.varint()?;
+        let mut gamepads = Vec::new();
+        for _ in 0..pad_count {
+            let mut pad = GamepadReport::new(self.varint()? as u32);
+            let buttons = self.varint()?;
+            for button in GamepadButton::ALL {
+                pad = pad.button(button, buttons & 1 << button as usize != 0);
+            }
+            for axis in GamepadAxis::ALL {
+                pad = pad.axis(axis, self.f32()?);
+            }
+            gamepads.push(pad);
+        }
+        Ok(gamepads)
+    }
+
+    fn input_state(&mut self) -> Result<InputState, String> {
+        let has_position = self.u8()? != 0;
+        let position = self.vec2()?;
+        let mouse_buttons = (0..self.varint()?)
+            .map(|_| self.button())
+            .collect::<Result<_, _>>()?;
+        let keys = (0..self.varint()?)
+            .map(|_| self.key())
+            .collect::<Result<_, _>>()?;
+        let key_mods = self.mods()?.0;
+        let touches = (0..self.varint()?)
+            .map(|_| match self.event()? {
+                InputEvent::Touch(phase, id, position) => Ok(Touch {
+                    id,
+                    phase,
+                    position,
+                }),
+                _ => Err("Expected a touch in recording".to_string()),
+            })
+            .collect::<Result<_, _>>()?;
+        let has_primary_touch = self.u8()? != 0;
+        let primary_touch = self.varint()?;
+        Ok(InputState {
+            mouse_position: has_position.then_some(position),
+            mouse_buttons,
+            keys,
+            key_mods,
+            touches,
+            primary_touch: has_primary_touch.then_some(primary_touch),
+            gamepads: self.gamepads()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> Recording {
+        let mods = KeyMods {
+            shift: true,
+            ..KeyMods::default()
+        };
+        let mut recording = Recording {
+            initial: InputState {
+                mouse_position: Some(vec2(7.0, 8.0)),
+                mouse_buttons: vec![MouseButton::Middle],
+                keys: vec![KeyCode::LeftShift, KeyCode::W],
+                key_mods: mods,
+                touches: vec![Touch {
+                    id: 9,
+                    phase: TouchPhase::Moved,
+                    position: vec2(1.0, 2.0),
+                }],
+                primary_touch: Some(9),
+                gamepads: vec![GamepadReport::new(1).button(GamepadButton::South, true)],
+            },
+            ..Recording::default()
+        };
+        recording.push_frame(RecordedFrame {
+            delta: 0.016,
+            screen_size: uvec2(800, 600),
+            events: vec![
+                InputEvent::MouseMotion(vec2(-3.5, 20.0)),
+                InputEvent::RawMouseMotion(vec2(0.5, -2.0)),
+                InputEvent::MouseDown(MouseButton::Right, vec2(1.0, 2.0)),
+                InputEvent::MouseUp(MouseButton::Right, vec2(1.0, 2.0)),
+                InputEvent::MouseWheel(vec2(0.0, -1.0)),
+                InputEvent::KeyDown(KeyCode::Escape, mods, true),
+                InputEvent::KeyUp(KeyCode::A, KeyMods::default()),
+                InputEvent::Char('ß', mods),
+                InputEvent::Touch(TouchPhase::Ended, 1 << 40, vec2(5.0, 6.0)),
+            ],
+            gamepads: vec![GamepadReport::new(3)
+                .button(GamepadButton::Start, true)
+                .axis(GamepadAxis::RightY, -0.25)],
+        });
+        recording.push_frame(RecordedFrame {
+            delta: 0.02,
+            screen_size: uvec2(1024, 768),
+            events: vec![],
+            gamepads: vec![],
+        });
+        recording
+    }
+
+    #[test]
+    fn round_trip() {
+        let recording = sample();
+        let bytes = recording.to_bytes();
+        assert_eq!(Recording::from_bytes(&bytes).unwrap(), recording);
+        // Version 1, without the initial state
+        assert_eq!(
+            Recording::from_bytes(b"OGTR\x01\x00").unwrap(),
+            Recording::default()
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_data() {
+        let bytes = sample().to_bytes();
+        assert!(Recording::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Recording::from_bytes(b"nope").is_err());
+    }
+
+    #[derive(Default)]
+    struct Counter {
+        updates: u32,
+        clicks: u32,
+        keys: Vec<KeyCode>,
+    }
+
+    impl Application for Counter {
+        fn update(&mut self, ctx: &Context, _delta: f32) {
+            self.updates += 1;
+            if ctx.is_key_pressed(KeyCode::Space) {
+                self.keys.push(KeyCode::Space);
+            }
+        }
+
+        fn render(&mut self, ctx: &Context, _delta: f32) {
+            if ctx.is_mouse_button_pressed(MouseButton::Left) {
+                self.clicks += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn replay_drives_application() {
+        let mut recording = Recording::default();
+        for events in [
+            vec![InputEvent::MouseDown(MouseButton::Left, vec2(1.0, 1.0))],
+            vec![
+                InputEvent::MouseUp(MouseButton::Left, vec2(1.0, 1.0)),
+                InputEvent::KeyDown(KeyCode::Space, KeyMods::default(), false),
+            ],
+            vec![InputEvent::Touch(TouchPhase::Started, 0, vec2(1.0, 1.0))],
+        ] {
+            recording.push_frame(RecordedFrame {
+                delta: 1.25 / 60.0,
+                screen_size: uvec2(100, 100),
+                events,
+                gamepads: vec![],
+            });
+        }
+        let mut context = Context::default();
+        let mut app = Counter::default();
+        replay(&recording, &mut context, &mut app);
+        assert_eq!(app.updates, 3);
+        assert_eq!(app.clicks, 2);
+        assert_eq!(app.keys, vec![KeyCode::Space]);
+        assert_eq!(context.screen_size(), uvec2(100, 100));
+    }
+
+    #[derive(Default)]
+    struct Releases(Vec<KeyCode>);
+
+    impl Application for Releases {
+        fn update(&mut self, ctx: &Context, _delta: f32) {
+            for key in [KeyCode::Space, KeyCode::A] {
+                if ctx.is_key_released(key) {
+                    self.0.push(key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn recording_starts_with_held_input() {
+        let mut context = Context::default();
+        context.input_event(InputEvent::KeyDown(
+            KeyCode::Space,
+            KeyMods::default(),
+            false,
+        ));
+        context.input_event(InputEvent::MouseDown(MouseButton::Right, vec2(4.0, 5.0)));
+        context.end_frame();
+        context.start_recording();
+        context.input_event(InputEvent::KeyUp(KeyCode::Space, KeyMods::default()));
+        context.run_frame(&mut Releases::default(), 1.0 / 60.0);
+        let bytes = context.stop_recording().unwrap().to_bytes();
+        let recording = Recording::from_bytes(&bytes).unwrap();
+
+        // Replaces whatever is held in the replaying session
+        let mut context = Context::default();
+        context.input_event(InputEvent::KeyDown(KeyCode::A, KeyMods::default(), false));
+        context.start_replay(recording.clone());
+        assert!(context.is_key_down(KeyCode::Space) && !context.is_key_down(KeyCode::A));
+        assert!(!context.is_key_pressed(KeyCode::Space));
+        assert!(context.is_mouse_button_down(MouseButton::Right));
+        assert!(!context.is_mouse_button_pressed(MouseButton::Right));
+        assert_eq!(context.mouse_position(), vec2(4.0, 5.0));
+
+        let mut app = Releases::default();
+        replay(&recording, &mut Context::default(), &mut app);
+        assert_eq!(app.0, vec![KeyCode::Space]);
+    }
+}
@@ -1,73 +1,201 @@
-use crate::Context;
-use miniquad::gl::*;
-
-unsafe fn compile_shader(
-    _context: &Context,
-    shader_id: GLuint,
-    shader: &str,
-    shader_type: &str,
-) -> Result<(), String> {
-    let len = shader.len();
-    glShaderSource(shader_id, 1, &(shader.as_ptr() as *const i8), &(len as i32));
-    glCompileShader(shader_id);
-
-    let mut result: GLint = 0;
-    glGetShaderiv(shader_id, GL_COMPILE_STATUS, &mut result);
-    let mut info_log_length: GLint = 0;
-    glGetShaderiv(shader_id, GL_INFO_LOG_LENGTH, &mut info_log_length);
-    if result == 0 && info_log_length > 0 {
-        let mut shader_error_message: Vec<u8> = Vec::with_capacity(info_log_length as usize);
-        glGetShaderInfoLog(
-            shader_id,
-            info_log_length,
-            0 as *mut i32,
-            shader_error_message.as_mut_ptr() as *mut i8,
-        );
-        shader_error_message.set_len(info_log_length as usize - 1);
-        return Err(format!(
-            "Error in {}-shader: {}",
-            shader_type,
-            String::from_utf8(shader_error_message).expect("Error message not parsable")
-        ));
+use crate::{Backend, Context, DrawCall, OgtError, ShaderId, Texture, Uniform, VertexLayout};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+struct ShaderHandle {
+    id: ShaderId,
+    backend: Rc<dyn Backend>,
+    uniforms: RefCell<Vec<(String, Uniform)>>,
+    textures: RefCell<Vec<(String, Texture)>>,
+}
+
+impl Drop for ShaderHandle {
+    fn drop(&mut self) {
+        self.backend.delete_shader(self.id);
+    }
+}
+
+/// Compiled shader program with its own uniforms. Clones share the program and the uniforms,
+/// so a shader given to a batch can still be changed afterwards. Uniforms are applied when
+/// the batch draws, not when sprites are added.
+#[derive(Clone)]
+pub struct Shader {
+    handle: Rc<ShaderHandle>,
+}
+
+impl PartialEq for Shader {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle.id == other.handle.id
     }
-    Ok(())
 }
 
-pub(crate) fn load_shaders(
-    context: &Context,
-    vertex_shader: &str,
-    fragment_shader: &str,
-) -> Result<GLuint, String> {
-    unsafe {
-        let vertex_shader_id = glCreateShader(GL_VERTEX_SHADER);
-        let fragment_shader_id = glCreateShader(GL_FRAGMENT_SHADER);
-
-        compile_shader(context, vertex_shader_id, vertex_shader, "VERTEX")?;
-        compile_shader(context, fragment_shader_id, fragment_shader, "FRAGMENT")?;
-
-        let program_id = glCreateProgram();
-        glAttachShader(program_id, vertex_shader_id);
-        glAttachShader(program_id, fragment_shader_id);
-        glLinkProgram(program_id);
-
-        let mut result: GLint = 0;
-        glGetProgramiv(program_id, GL_LINK_STATUS, &mut result);
-        let mut info_log_length = 0;
-        glGetProgramiv(program_id, GL_INFO_LOG_LENGTH, &mut info_log_length);
-        if result == 0 && info_log_length > 0 {
-            let mut program_error_message: Vec<u8> = Vec::with_capacity(info_log_length as usize);
-            glGetProgramInfoLog(
-                program_id,
-                info_log_length,
-                0 as *mut i32,
-                program_error_message.as_mut_ptr() as *mut i8,
-            );
-            program_error_message.set_len(info_log_length as usize);
-            return Err(
-                String::from_utf8(program_error_message).expect("Error message not parsable")
-            );
+impl Shader {
+    /// GLSL sources, attributes are matched with `layout` by name. See
+    /// [`crate::SpriteBatch::create_shader`] and [`crate::ShapeBatch::create_shader`] for
+    /// shaders the batches can use.
+    pub fn new(
+        context: &Context,
+        vertex_shader: &str,
+        fragment_shader: &str,
+        layout: &VertexLayout,
+    ) -> Result<Self, OgtError> {
+        let backend = context.backend().clone();
+        let id = backend.create_shader(vertex_shader, fragment_shader, layout)?;
+        Ok(Self {
+            handle: Rc::new(ShaderHandle {
+                id,
+                backend,
+                uniforms: RefCell::default(),
+                textures: RefCell::default(),
+            }),
+        })
+    }
+
+    pub fn id(&self) -> ShaderId {
+        self.handle.id
+    }
+
+    /// Sets the uniform `name`, e.g. `shader.set_uniform("flash", 0.5)`. Unknown names are
+    /// ignored, just like GL does.
+    pub fn set_uniform(&self, name: &str, value: impl Into<Uniform>) {
+        let value = value.into();
+        let mut uniforms = self.handle.uniforms.borrow_mut();
+        match uniforms.iter_mut().find(|(existing, _)| existing == name) {
+            Some((_, existing)) => *existing = value,
+            None => uniforms.push((name.to_string(), value)),
         }
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<Uniform> {
+        self.handle
+            .uniforms
+            .borrow()
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Binds `texture` to the sampler `name`, in addition to the texture of the batch.
+    pub fn set_texture(&self, name: &str, texture: &Texture) {
+        let mut textures = self.handle.textures.borrow_mut();
+        match textures.iter_mut().find(|(existing, _)| existing == name) {
+            Some((_, existing)) => *existing = texture.clone(),
+            None => textures.push((name.to_string(), texture.clone())),
+        }
+    }
+
+    /// Draws `call` with this shader, adding its uniforms and textures. The extra textures
+    /// go to the units after the ones of `call`.
+    pub(crate) fn draw(&self, call: &DrawCall) {
+        let extra = self.handle.textures.borrow();
+        let textures: Vec<_> = call
+            .textures
+            .iter()
+            .copied()
+            .chain(extra.iter().map(|(_, texture)| texture.id()))
+            .collect();
+        let own = self.handle.uniforms.borrow();
+        let uniforms: Vec<_> = call
+            .uniforms
+            .iter()
+            .copied()
+            .chain(own.iter().map(|(name, value)| (name.as_str(), *value)))
+            .chain(extra.iter().enumerate().map(|(i, (name, _))| {
+                (
+                    name.as_str(),
+                    Uniform::Int((call.textures.len() + i) as i32),
+                )
+            }))
+            .collect();
+        self.handle.backend.draw(&DrawCall {
+            shader: self.handle.id,
+            textures: &textures,
+            uniforms: &uniforms,
+            ..*call
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::math::*;
+    use crate::SpriteBatch;
+
+    #[test]
+    fn uniforms_are_shared_between_clones() {
+        let context = Context::default();
+        let shader = SpriteBatch::create_shader(&context, "").unwrap();
+        let clone = shader.clone();
+        shader.set_uniform("flash", 0.5);
+        clone.set_uniform("flash", 1.0);
+        shader.set_uniform("offset", vec2(1.0, 2.0));
+        assert_eq!(shader.uniform("flash"), Some(Uniform::Float(1.0)));
+        assert_eq!(clone.uniform("offset"), Some(Uniform::Vec2(vec2(1.0, 2.0))));
+        assert_eq!(clone.uniform("missing"), None);
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn flash_shader_on_gl() {
+        use crate::{TextureBuilder, TextureFilter, BLACK, RED, WHITE};
+
+        let Some(mut headless) = crate::Headless::for_gl_test(4, 2) else {
+            return;
+        };
+        let ctx = headless.context();
+        ctx.clear_screen(BLACK);
+        let texture = TextureBuilder::from_bytes(&RED.0.repeat(4), 2, 2)
+            .filter(TextureFilter::Nearest)
+            .build(ctx);
+        let white = TextureBuilder::from_bytes(&WHITE.0, 1, 1).build(ctx);
+        let shader = SpriteBatch::create_shader(
+            ctx,
+            r#"#version 100
+varying lowp vec4 fragmentColor;
+varying lowp vec2 texCoord;
+
+uniform sampler2D Tex;
+uniform sampler2D overlay;
+uniform lowp float flash;
+
+void main() {
+    lowp vec4 color = fragmentColor * texture2D(Tex, texCoord);
+    gl_FragColor = mix(color, texture2D(overlay, texCoord), flash);
+}
+"#,
+        )
+        .unwrap();
+        shader.set_texture("overlay", &white);
+
+        let mut sprites = SpriteBatch::new(ctx);
+        sprites.set_model_view_projection_matrix(Mat4::orthographic_rh_gl(
+            0.0, 4.0, 0.0, 2.0, -1.0, 1.0,
+        ));
+        sprites.set_shader(ctx, Some(shader.clone()));
+        sprites.add(
+            ctx,
+            &texture,
+            texture.as_region(),
+            WHITE,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        shader.set_uniform("flash", 0.0);
+        sprites.draw(ctx);
+        sprites.add(
+            ctx,
+            &texture,
+            texture.as_region(),
+            WHITE,
+            Vec2::ZERO,
+            vec2(2.0, 0.0),
+        );
+        shader.set_uniform("flash", 1.0);
+        sprites.draw(ctx);
 
-        Ok(program_id)
+        let image = headless.read_pixels();
+        assert_eq!(image.get_pixel(0, 0).0, RED.0);
+        assert_eq!(image.get_pixel(3, 1).0, WHITE.0);
     }
 }
@@ -1,2 +1,333 @@
-#[cfg(feature = "miniquad")]
-pub use crate::backend::miniquad::shape_batch::*;
+use crate::backend::as_bytes;
+use crate::{
+    math::*, Backend, BlendMode, BufferId, BufferKind, BufferUsage, Color, Context, DepthMode,
+    DrawCall, OgtError, Shader, Uniform, VertexAttribute, VertexFormat, VertexLayout,
+};
+use memoffset::offset_of;
+use std::mem::size_of;
+use std::rc::Rc;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Vertex {
+    pos: [f32; 2],
+    color: [u8; 4],
+}
+
+impl Vertex {
+    fn from_vec_color(v: Vec2, col: Color) -> Self {
+        Self {
+            pos: v.to_array(),
+            color: col.0,
+        }
+    }
+}
+
+pub struct ShapeBatch {
+    max_triangles: usize,
+    triangle_count: usize,
+    backend: Rc<dyn Backend>,
+    vertex_buffer: BufferId,
+    array_buffer_data: Vec<Vertex>,
+    default_shader: Shader,
+    shader: Option<Shader>,
+    blend_mode: BlendMode,
+    model_view_projection: Mat4,
+}
+
+impl ShapeBatch {
+    pub fn new(context: &Context) -> ShapeBatch {
+        Self::with_max_triangles(context, 10_000)
+    }
+
+    pub fn try_new(context: &Context) -> Result<Self, OgtError> {
+        Self::try_with_max_triangles(context, 10_000)
+    }
+
+    fn triangles<const N: usize>(&mut self, context: &Context, vertices: [(Vec2, Color); N]) {
+        assert!(N.is_multiple_of(3));
+        if self.triangle_count + N / 3 >= self.max_triangles {
+            self.draw(context);
+        }
+        self.triangle_count += N / 3;
+        let vertices = vertices.map(|(v, c)| Vertex::from_vec_color(v, c));
+        self.array_buffer_data.extend(vertices);
+    }
+
+    pub fn add_triangle(&mut self, context: &Context, v1: Vec2, v2: Vec2, v3: Vec2, color: Color) {
+        self.triangles(context, [(v1, color), (v2, color), (v3, color)]);
+    }
+
+    pub fn add_line(
+        &mut self,
+        context: &Context,
+        v1: Vec2,
+        v2: Vec2,
+        thickness: f32,
+        color: Color,
+    ) {
+        let dv = v2 - v1;
+        let perp = dv.perp().normalize() * thickness / 2.0;
+        self.triangles(
+            context,
+            [
+                (v1 + perp, color),
+                (v2 + perp, color),
+                (v2 - perp, color),
+                (v2 - perp, color),
+                (v1 - perp, color),
+                (v1 + perp, color),
+            ],
+        );
+    }
+
+    pub fn add_rect(
+        &mut self,
+        context: &Context,
+        top_left: Vec2,
+        bottom_right: Vec2,
+        thickness: f32,
+        color: Color,
+    ) {
+        self.add_line(
+            context,
+            top_left - vec2(thickness / 2.0, 0.0),
+            vec2(bottom_right.x + thickness / 2.0, top_left.y),
+            thickness,
+            color,
+        );
+        self.add_line(
+            context,
+            vec2(bottom_right.x, top_left.y),
+            bottom_right,
+            thickness,
+            color,
+        );
+        self.add_line(
+            context,
+            bottom_right + vec2(thickness / 2.0, 0.0),
+            vec2(top_left.x - thickness / 2.0, bottom_right.y),
+            thickness,
+            color,
+        );
+        self.add_line(
+            context,
+            vec2(top_left.x, bottom_right.y),
+            top_left,
+            thickness,
+            color,
+        );
+    }
+
+    pub fn add_filled_rect(
+        &mut self,
+        context: &Context,
+        top_left: Vec2,
+        bottom_right: Vec2,
+        color: Color,
+    ) {
+        self.triangles(
+            context,
+            [
+                (top_left, color),
+                (vec2(bottom_right.x, top_left.y), color),
+                (bottom_right, color),
+                (bottom_right, color),
+                (vec2(top_left.x, bottom_right.y), color),
+                (top_left, color),
+            ],
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_circle(
+        &mut self,
+        context: &Context,
+        center: Vec2,
+        radius: f32,
+        thickness: f32,
+        segments: usize,
+        start: f32,
+        end: f32,
+        color: Color,
+    ) {
+        let inner = vec2(radius - thickness, 0.0);
+        let outer = vec2(radius + thickness, 0.0);
+        let transform = Mat2::from_angle(start);
+        let mut last_outer = center + transform.mul_vec2(outer);
+        let mut last_inner = center + transform.mul_vec2(inner);
+        for i in 0..=segments {
+            let transform = Mat2::from_angle(start + i as f32 * (end - start) / segments as f32);
+            let next_outer = center + transform.mul_vec2(outer);
+            let next_inner = center + transform.mul_vec2(inner);
+            self.triangles(
+                context,
+                [
+                    (last_outer, color),
+                    (next_outer, color),
+                    (next_inner, color),
+                    (next_inner, color),
+                    (last_inner, color),
+                    (last_outer, color),
+                ],
+            );
+            last_outer = next_outer;
+            last_inner = next_inner;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_filled_circle(
+        &mut self,
+        context: &Context,
+        center: Vec2,
+        radius: f32,
+        segments: usize,
+        start: f32,
+        end: f32,
+        color: Color,
+    ) {
+        let boundary = vec2(radius, 0.0);
+        let transform = Mat2::from_angle(start);
+        let mut last = center + transform.mul_vec2(boundary);
+        for i in 0..=segments {
+            let transform = Mat2::from_angle(start + i as f32 * (end - start) / segments as f32);
+            let next = center + transform.mul_vec2(boundary);
+            self.triangles(context, [(center, color), (last, color), (next, color)]);
+            last = next;
+        }
+    }
+
+    pub fn draw(&mut self, _context: &Context) {
+        if self.triangle_count == 0 {
+            return;
+        }
+        self.backend
+            .update_buffer(self.vertex_buffer, as_bytes(&self.array_buffer_data));
+        self.shader().draw(&DrawCall {
+            shader: self.default_shader.id(),
+            vertices: self.vertex_buffer,
+            indices: None,
+            count: 3 * self.triangle_count,
+            instances: None,
+            textures: &[],
+            uniforms: &[(
+                "viewProjectionMatrix",
+                Uniform::Mat4(self.model_view_projection),
+            )],
+            blend: self.blend_mode,
+            depth: DepthMode::Disabled,
+        });
+        self.triangle_count = 0;
+        self.array_buffer_data.clear();
+    }
+
+    /// Panics if the shader can't be compiled, see [`ShapeBatch::try_with_max_triangles`].
+    pub fn with_max_triangles(context: &Context, max: usize) -> Self {
+        Self::try_with_max_triangles(context, max).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_with_max_triangles(context: &Context, max: usize) -> Result<Self, OgtError> {
+        let backend = context.backend().clone();
+        let default_shader = Shader::new(context, VERTEX_SHADER, FRAGMENT_SHADER, &layout())?;
+        let vertex_buffer = backend.create_buffer(BufferKind::Vertex, BufferUsage::Stream);
+        Ok(Self {
+            max_triangles: max,
+            triangle_count: 0,
+            backend,
+            vertex_buffer,
+            array_buffer_data: Vec::with_capacity(3 * max),
+            default_shader,
+            shader: None,
+            blend_mode: BlendMode::Alpha,
+            model_view_projection: Mat4::IDENTITY,
+        })
+    }
+
+    pub fn set_model_view_projection_matrix(&mut self, matrix: Mat4) {
+        self.model_view_projection = matrix;
+    }
+
+    /// Shader with a custom fragment stage for [`ShapeBatch::set_shader`]. It gets these
+    /// inputs:
+    /// ```glsl
+    /// #version 100
+    /// varying lowp vec4 fragmentColor;
+    /// ```
+    pub fn create_shader(context: &Context, fragment_shader: &str) -> Result<Shader, OgtError> {
+        Shader::new(context, VERTEX_SHADER, fragment_shader, &layout())
+    }
+
+    /// Draws with `shader` instead of the default one, `None` switches back.
+    /// Pending shapes are drawn first if the shader changes.
+    pub fn set_shader(&mut self, context: &Context, shader: Option<Shader>) {
+        if shader != self.shader {
+            self.draw(context);
+            self.shader = shader;
+        }
+    }
+
+    /// How shapes are blended with the background, [`BlendMode::Alpha`] by default.
+    /// Pending shapes are drawn first if the mode changes.
+    pub fn set_blend_mode(&mut self, context: &Context, mode: BlendMode) {
+        if mode != self.blend_mode {
+            self.draw(context);
+            self.blend_mode = mode;
+        }
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    fn shader(&self) -> &Shader {
+        self.shader.as_ref().unwrap_or(&self.default_shader)
+    }
+}
+
+impl Drop for ShapeBatch {
+    fn drop(&mut self) {
+        self.backend.delete_buffer(self.vertex_buffer);
+    }
+}
+
+fn layout() -> VertexLayout {
+    VertexLayout {
+        stride: size_of::<Vertex>(),
+        attributes: vec![
+            VertexAttribute {
+                name: "vertex_pos",
+                format: VertexFormat::Float2,
+                offset: offset_of!(Vertex, pos),
+            },
+            VertexAttribute {
+                name: "vertex_color",
+                format: VertexFormat::UByte4Norm,
+                offset: offset_of!(Vertex, color),
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec2 vertex_pos;
+attribute vec4 vertex_color;
+
+varying lowp vec4 fragmentColor;
+
+uniform mat4 viewProjectionMatrix;
+
+void main() {
+    gl_Position = viewProjectionMatrix * vec4(vertex_pos, 0.0, 1.0);
+    fragmentColor = vertex_color;
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 100
+varying lowp vec4 fragmentColor;
+
+void main() {
+    gl_FragColor = fragmentColor;
+}
+"#;
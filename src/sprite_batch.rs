@@ -1,10 +1,13 @@
+use crate::backend::as_bytes;
 use crate::texture::*;
-use crate::{Color, Context};
+use crate::{
+    Backend, BlendMode, BufferId, BufferKind, BufferUsage, Color, Context, DepthMode, DrawCall,
+    OgtError, Shader, TextureId, Uniform, VertexAttribute, VertexFormat, VertexLayout,
+};
 use glam::f32::*;
 use memoffset::*;
-use miniquad::gl::*;
-use std::ffi::CString;
-use std::mem::{size_of, size_of_val};
+use std::mem::size_of;
+use std::rc::Rc;
 
 pub trait Transform2D {
     fn transform(&self, origin: Vec2, region: Region) -> [Vec3; 4];
@@ -56,18 +59,59 @@ struct Vertex {
     pos: [f32; 3],
     uv: [f32; 2],
     color: [u8; 4],
+    /// Texture unit of the draw call
+    texture: f32,
+}
+
+/// Textures one draw call of the default shader can sample from, if the backend has enough
+/// texture units.
+const MAX_TEXTURES: usize = 8;
+/// Sprites `u16` indices can address
+const MAX_SPRITES_INDEX16: usize = 1 << 14;
+const SAMPLERS: [&str; MAX_TEXTURES] = [
+    "Tex", "Tex1", "Tex2", "Tex3", "Tex4", "Tex5", "Tex6", "Tex7",
+];
+
+/// Order in which [`SpriteBatch::draw`] submits the pending sprites. A draw call can use
+/// several textures, a new one is needed whenever that limit is exceeded. With a custom shader
+/// the limit is one, so every change of texture in that order costs a draw call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortMode {
+    /// Submission order, overlapping sprites are drawn as added.
+    #[default]
+    Submission,
+    /// Sprites sharing a texture are drawn together, in order of first use. Only for sprites
+    /// that don't overlap or use the depth test.
+    Texture,
+    /// Back to front by the z of a `Vec3` transform, sprites at the same depth keep their
+    /// submission order.
+    Depth,
+}
+
+#[derive(Copy, Clone)]
+struct Sprite {
+    /// Index into the textures of the batch
+    texture: usize,
+    /// Whether its color or texture has translucent pixels
+    translucent: bool,
 }
 
 pub struct SpriteBatch {
-    max_sprites: u16,
-    sprite_count: u16,
-    vertex_array_id: GLuint,
-    array_buffer_id: GLuint,
-    element_buffer_id: GLuint,
+    max_sprites: usize,
+    backend: Rc<dyn Backend>,
+    vertex_buffer: BufferId,
+    index_buffer: BufferId,
     array_buffer_data: Vec<Vertex>,
-    shader_id: GLuint,
-    texture: Option<Texture>,
-    uniform_mvp: GLint,
+    sprites: Vec<Sprite>,
+    /// Textures of the pending sprites
+    textures: Vec<Texture>,
+    default_shader: Shader,
+    /// Textures the default shader can sample in one draw call
+    max_textures: usize,
+    shader: Option<Shader>,
+    blend_mode: BlendMode,
+    depth_test: bool,
+    sort_mode: SortMode,
     model_view_projection: Mat4,
 }
 
@@ -76,195 +120,344 @@ impl SpriteBatch {
         Self::with_max_sprites(gl, 10_000)
     }
 
-    pub fn draw(&mut self, context: &Context) {
-        if self.sprite_count == 0 {
+    pub fn try_new(context: &Context) -> Result<Self, OgtError> {
+        Self::try_with_max_sprites(context, 10_000)
+    }
+
+    /// Draws the pending sprites, with as few draw calls as the texture units allow.
+    pub fn draw(&mut self, _context: &Context) {
+        if self.sprites.is_empty() {
             return;
         }
-        unsafe {
-            glUseProgram(self.shader_id);
-            glUniformMatrix4fv(
-                self.uniform_mvp,
-                1,
-                GL_FALSE as u8,
-                self.model_view_projection.to_cols_array().as_ptr(),
-            );
-            glBindVertexArray(self.vertex_array_id);
-            glBindBuffer(GL_ELEMENT_ARRAY_BUFFER, self.element_buffer_id);
-            glBindBuffer(GL_ARRAY_BUFFER, self.array_buffer_id);
-            // According to https://thothonegan.tumblr.com/post/135193767243/glbuffersubdata-vs-glbufferdata
-            // this is better as the GPU can work on the buffer without blocking
-            glBufferData(
-                GL_ARRAY_BUFFER,
-                (size_of_val(&self.array_buffer_data[..])) as GLsizeiptr,
-                self.array_buffer_data.as_ptr() as *const GLvoid,
-                GL_STREAM_DRAW,
-            );
-            // debug_assert_eq!(
-            //     self.sprite_count * 4 * size_of::<Vertex>(),
-            //     size_of_val(&self.array_buffer_data[..])
-            // );
-            // glBufferSubData(
-            //     GL_ARRAY_BUFFER,
-            //     0,
-            //     (self.sprite_count * 4 * size_of::<Vertex>()) as GLsizeiptr,
-            //     self.array_buffer_data.as_ptr() as *const GLvoid,
-            // );
-            self.texture
-                .as_ref()
-                .expect("Texture must be set on SpriteBatch")
-                .bind(context);
-            glDrawElements(
-                GL_TRIANGLES,
-                self.sprite_count as i32 * 6,
-                GL_UNSIGNED_SHORT,
-                0 as *const GLvoid,
-            );
+        if self.depth_test {
+            let (mut opaque, mut translucent): (Vec<usize>, Vec<usize>) =
+                (0..self.sprites.len()).partition(|&sprite| !self.sprites[sprite].translucent);
+            self.sort(&mut opaque);
+            // Translucent sprites always go back to front
+            translucent.sort_by(|&a, &b| self.depth(b).total_cmp(&self.depth(a)));
+            self.submit(&opaque, DepthMode::TestAndWrite);
+            self.submit(&translucent, DepthMode::Test);
+        } else if self.sort_mode == SortMode::Submission && self.textures.len() <= self.units() {
+            // Vertices already refer to the units of the pending textures, no need to gather
+            let textures: Vec<_> = self.textures.iter().map(Texture::id).collect();
+            self.submit_vertices(&textures, &self.array_buffer_data, DepthMode::Disabled);
+        } else {
+            let mut order: Vec<usize> = (0..self.sprites.len()).collect();
+            self.sort(&mut order);
+            self.submit(&order, DepthMode::Disabled);
         }
-        self.sprite_count = 0;
         self.array_buffer_data.clear();
+        self.sprites.clear();
+        self.textures.clear();
+    }
+
+    fn sort(&self, order: &mut [usize]) {
+        match self.sort_mode {
+            SortMode::Submission => {}
+            SortMode::Texture => order.sort_by_key(|&sprite| self.sprites[sprite].texture),
+            SortMode::Depth => order.sort_by(|&a, &b| self.depth(b).total_cmp(&self.depth(a))),
+        }
+    }
+
+    /// Depth of the center of `sprite` after projection, larger is farther away.
+    fn depth(&self, sprite: usize) -> f32 {
+        let center = self.array_buffer_data[4 * sprite..4 * sprite + 4]
+            .iter()
+            .map(|vertex| Vec3::from(vertex.pos))
+            .sum::<Vec3>()
+            / 4.0;
+        self.model_view_projection.project_point3(center).z
+    }
+
+    /// Textures one draw call can use with the current shader.
+    fn units(&self) -> usize {
+        match self.shader {
+            Some(_) => 1,
+            None => self.max_textures,
+        }
+    }
+
+    /// Draws the sprites in `order`, starting a new draw call whenever the texture units run
+    /// out.
+    fn submit(&self, mut order: &[usize], depth: DepthMode) {
+        let units = self.units();
+        let mut vertices = Vec::with_capacity(4 * order.len());
+        while !order.is_empty() {
+            let mut textures = Vec::with_capacity(units);
+            let mut count = 0;
+            for &sprite in order {
+                let texture = self.textures[self.sprites[sprite].texture].id();
+                let unit = match textures.iter().position(|&bound| bound == texture) {
+                    Some(unit) => unit,
+                    None if textures.len() < units => {
+                        textures.push(texture);
+                        textures.len() - 1
+                    }
+                    None => break,
+                };
+                vertices.extend(
+                    self.array_buffer_data[4 * sprite..4 * sprite + 4]
+                        .iter()
+                        .map(|vertex| Vertex {
+                            texture: unit as f32,
+                            ..*vertex
+                        }),
+                );
+                count += 1;
+            }
+            self.submit_vertices(&textures, &vertices, depth);
+            vertices.clear();
+            order = &order[count..];
+        }
+    }
+
+    fn submit_vertices(&self, textures: &[TextureId], vertices: &[Vertex], depth: DepthMode) {
+        self.backend
+            .update_buffer(self.vertex_buffer, as_bytes(vertices));
+        let mut uniforms = vec![(
+            "viewProjectionMatrix",
+            Uniform::Mat4(self.model_view_projection),
+        )];
+        if self.shader.is_none() {
+            uniforms.extend(
+                SAMPLERS
+                    .iter()
+                    .take(textures.len())
+                    .enumerate()
+                    .map(|(unit, &name)| (name, Uniform::Int(unit as i32))),
+            );
+        }
+        self.shader().draw(&DrawCall {
+            shader: self.default_shader.id(),
+            vertices: self.vertex_buffer,
+            indices: Some(self.index_buffer),
+            count: vertices.len() / 4 * 6,
+            instances: None,
+            textures,
+            uniforms: &uniforms,
+            blend: self.blend_mode,
+            depth,
+        });
     }
 
+    /// Adds the `sprite` region of `texture`. Sprites may use different textures, the batch
+    /// splits them into draw calls as needed, see [`SpriteBatch::set_sort_mode`].
     pub fn add<X: Transform2D>(
         &mut self,
         gl: &Context,
+        texture: &Texture,
         sprite: Region,
         color: Color,
         origin: Vec2,
         transform: X,
     ) {
-        if self.sprite_count == self.max_sprites {
+        if self.sprites.len() == self.max_sprites {
             self.draw(gl);
         }
-        let texture_data = &self
-            .texture
-            .as_ref()
-            .expect("Texture must be set on SpriteBatch");
-        let (width, height) = (texture_data.width as f32, texture_data.height as f32);
+        let index = match self.textures.iter().rposition(|pending| pending == texture) {
+            Some(index) => index,
+            None => {
+                self.textures.push(texture.clone());
+                self.textures.len() - 1
+            }
+        };
+        let (width, height) = (texture.width as f32, texture.height as f32);
+        let (mut top, mut bottom) = (sprite.top_left[1] / height, sprite.bottom_right[1] / height);
+        if texture.is_flipped() {
+            (top, bottom) = (1.0 - top, 1.0 - bottom);
+        }
         let vertices = transform.transform(origin, sprite);
         let to_append = vertices
             .iter()
             .zip([
-                [sprite.top_left[0] / width, sprite.bottom_right[1] / height],
-                [
-                    sprite.bottom_right[0] / width,
-                    sprite.bottom_right[1] / height,
-                ],
-                [sprite.bottom_right[0] / width, sprite.top_left[1] / height],
-                [sprite.top_left[0] / width, sprite.top_left[1] / height],
+                [sprite.top_left[0] / width, bottom],
+                [sprite.bottom_right[0] / width, bottom],
+                [sprite.bottom_right[0] / width, top],
+                [sprite.top_left[0] / width, top],
             ])
             .map(|(pos, uv)| Vertex {
                 pos: [pos.x, pos.y, pos.z],
                 uv,
                 color: color.0,
+                texture: index as f32,
             });
         self.array_buffer_data.extend(to_append);
-        self.sprite_count += 1;
+        self.sprites.push(Sprite {
+            texture: index,
+            translucent: color.0[3] < 255 || !texture.is_opaque(),
+        });
     }
 
-    pub fn with_max_sprites(context: &Context, max: u16) -> Self {
-        let shader_id =
-            crate::shader::load_shaders(&context, VERTEX_SHADER, FRAGMENT_SHADER).unwrap();
-        let matrix_id = unsafe {
-            let mvp = CString::new("viewProjectionMatrix").unwrap();
-            glGetUniformLocation(shader_id, mvp.as_ptr())
-        };
-        let array_buffer_data = Vec::with_capacity(max as usize * 4);
-        let mut element_buffer_data = Vec::with_capacity(max as usize * 6);
-        for i in (0..max * 4).step_by(4) {
-            element_buffer_data.push(i as u16);
-            element_buffer_data.push(i as u16 + 1);
-            element_buffer_data.push(i as u16 + 3);
-            element_buffer_data.push(i as u16 + 1);
-            element_buffer_data.push(i as u16 + 2);
-            element_buffer_data.push(i as u16 + 3);
-        }
-        let mut vertex_array_id: GLuint = 0;
-        let mut array_buffer_id: GLuint = 0;
-        let mut element_buffer_id: GLuint = 0;
-        unsafe {
-            glGenVertexArrays(1, &mut vertex_array_id as *mut GLuint);
-            glBindVertexArray(vertex_array_id);
-            glEnableVertexAttribArray(0);
-            glEnableVertexAttribArray(1);
-            glEnableVertexAttribArray(2);
-
-            glGenBuffers(1, &mut array_buffer_id as *mut GLuint);
-            glBindBuffer(GL_ARRAY_BUFFER, array_buffer_id);
-            // glBufferData(
-            //     GL_ARRAY_BUFFER,
-            //     (size_of::<Vertex>() * 4 * max) as GLsizeiptr,
-            //     0 as *const GLvoid,
-            //     GL_DYNAMIC_DRAW,
-            // );
-            let stride = size_of::<Vertex>() as i32;
-            glVertexAttribPointer(
-                0,
-                3,
-                GL_FLOAT,
-                GL_FALSE as u8,
-                stride,
-                offset_of!(Vertex, pos) as *const GLvoid,
-            );
-            glVertexAttribPointer(
-                1,
-                2,
-                GL_FLOAT,
-                GL_FALSE as u8,
-                stride,
-                offset_of!(Vertex, uv) as *const GLvoid,
-            );
-            glVertexAttribPointer(
-                2,
-                4,
-                GL_UNSIGNED_BYTE,
-                GL_TRUE as u8,
-                stride,
-                offset_of!(Vertex, color) as *const GLvoid,
-            );
+    /// Panics if the shader can't be compiled, see [`SpriteBatch::try_with_max_sprites`].
+    pub fn with_max_sprites(context: &Context, max: usize) -> Self {
+        Self::try_with_max_sprites(context, max).unwrap_or_else(|err| panic!("{err}"))
+    }
 
-            glGenBuffers(1, &mut element_buffer_id as *mut GLuint);
-            glBindBuffer(GL_ELEMENT_ARRAY_BUFFER, element_buffer_id);
-            glBufferData(
-                GL_ELEMENT_ARRAY_BUFFER,
-                std::mem::size_of_val(&element_buffer_data[..]) as GLsizeiptr,
-                element_buffer_data.as_ptr() as *const GLvoid,
-                GL_STATIC_DRAW,
-            );
-        }
-        Self {
+    /// Batch drawing once `max` sprites are pending. More than 16384 need 32 bit indices, if
+    /// the backend doesn't support them that's the limit, see [`SpriteBatch::max_sprites`].
+    pub fn try_with_max_sprites(context: &Context, max: usize) -> Result<Self, OgtError> {
+        let backend = context.backend().clone();
+        let max_textures = backend.max_texture_units().clamp(1, MAX_TEXTURES);
+        let default_shader = Shader::new(
+            context,
+            VERTEX_SHADER,
+            &fragment_shader(max_textures),
+            &layout(),
+        )?;
+        let index32 = max > MAX_SPRITES_INDEX16 && backend.supports_index32();
+        let max = match index32 {
+            true => max,
+            false => max.min(MAX_SPRITES_INDEX16),
+        };
+        let array_buffer_data = Vec::with_capacity(max * 4);
+        let indices = (0..max as u32).flat_map(|i| [0, 1, 3, 1, 2, 3].map(|corner| 4 * i + corner));
+        let vertex_buffer = backend.create_buffer(BufferKind::Vertex, BufferUsage::Stream);
+        let index_buffer = if index32 {
+            let index_buffer = backend.create_buffer(BufferKind::Index32, BufferUsage::Static);
+            let element_buffer_data: Vec<u32> = indices.collect();
+            backend.update_buffer(index_buffer, as_bytes(&element_buffer_data));
+            index_buffer
+        } else {
+            let index_buffer = backend.create_buffer(BufferKind::Index, BufferUsage::Static);
+            let element_buffer_data: Vec<u16> = indices.map(|index| index as u16).collect();
+            backend.update_buffer(index_buffer, as_bytes(&element_buffer_data));
+            index_buffer
+        };
+        Ok(Self {
             max_sprites: max,
-            sprite_count: 0,
-            vertex_array_id,
-            array_buffer_id,
-            element_buffer_id,
+            backend,
+            vertex_buffer,
+            index_buffer,
             array_buffer_data,
-            shader_id,
-            texture: None,
-            uniform_mvp: matrix_id,
+            sprites: Vec::with_capacity(max),
+            textures: Vec::new(),
+            default_shader,
+            max_textures,
+            shader: None,
+            blend_mode: BlendMode::Alpha,
+            depth_test: false,
+            sort_mode: SortMode::Submission,
             model_view_projection: Mat4::IDENTITY,
-        }
+        })
     }
 
-    pub fn set_texture(&mut self, texture: Texture) {
-        assert!(
-            self.texture.as_ref() == Some(&texture) || self.sprite_count == 0,
-            "Texture must be set before drawing (did you forget to actually call draw?)"
-        );
-        self.texture = Some(texture);
+    /// How many sprites are drawn at once at most.
+    pub fn max_sprites(&self) -> usize {
+        self.max_sprites
     }
 
     pub fn set_model_view_projection_matrix(&mut self, matrix: Mat4) {
         self.model_view_projection = matrix;
     }
+
+    /// Shader with a custom fragment stage for [`SpriteBatch::set_shader`], e.g. for flash,
+    /// dissolve or palette effects. It gets these inputs, see [`Shader::set_texture`] for
+    /// more samplers:
+    /// ```glsl
+    /// #version 100
+    /// varying lowp vec4 fragmentColor;
+    /// varying lowp vec2 texCoord;
+    /// // The texture of the sprite
+    /// uniform sampler2D Tex;
+    /// ```
+    pub fn create_shader(context: &Context, fragment_shader: &str) -> Result<Shader, OgtError> {
+        Shader::new(context, VERTEX_SHADER, fragment_shader, &layout())
+    }
+
+    /// Draws with `shader` instead of the default one, `None` switches back.
+    /// Pending sprites are drawn first if the shader changes.
+    pub fn set_shader(&mut self, context: &Context, shader: Option<Shader>) {
+        if shader != self.shader {
+            self.draw(context);
+            self.shader = shader;
+        }
+    }
+
+    /// How sprites are blended with the background, [`BlendMode::Alpha`] by default.
+    /// Pending sprites are drawn first if the mode changes.
+    pub fn set_blend_mode(&mut self, context: &Context, mode: BlendMode) {
+        if mode != self.blend_mode {
+            self.draw(context);
+            self.blend_mode = mode;
+        }
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Sorts sprites by their depth, the z of a `Vec3` transform. Opaque sprites (both
+    /// texture and color) are drawn first with the depth test, translucent ones follow back
+    /// to front. Sorting only happens within one [`SpriteBatch::draw`]. Needs a depth buffer:
+    /// the window and [`crate::Context::clear_screen`] take care of that, render targets need
+    /// [`crate::RenderTargetBuilder::depth_stencil`].
+    pub fn set_depth_test(&mut self, context: &Context, enabled: bool) {
+        if enabled != self.depth_test {
+            self.draw(context);
+            self.depth_test = enabled;
+        }
+    }
+
+    /// Takes effect at the next [`SpriteBatch::draw`], [`SortMode::Submission`] by default.
+    /// With the depth test only the opaque sprites are sorted this way.
+    pub fn set_sort_mode(&mut self, mode: SortMode) {
+        self.sort_mode = mode;
+    }
+
+    pub fn sort_mode(&self) -> SortMode {
+        self.sort_mode
+    }
+
+    fn shader(&self) -> &Shader {
+        self.shader.as_ref().unwrap_or(&self.default_shader)
+    }
+}
+
+impl Drop for SpriteBatch {
+    fn drop(&mut self) {
+        self.backend.delete_buffer(self.vertex_buffer);
+        self.backend.delete_buffer(self.index_buffer);
+    }
+}
+
+fn layout() -> VertexLayout {
+    VertexLayout {
+        stride: size_of::<Vertex>(),
+        attributes: vec![
+            VertexAttribute {
+                name: "vertex_pos",
+                format: VertexFormat::Float3,
+                offset: offset_of!(Vertex, pos),
+            },
+            VertexAttribute {
+                name: "tex_uv",
+                format: VertexFormat::Float2,
+                offset: offset_of!(Vertex, uv),
+            },
+            VertexAttribute {
+                name: "vertex_color",
+                format: VertexFormat::UByte4Norm,
+                offset: offset_of!(Vertex, color),
+            },
+            VertexAttribute {
+                name: "tex_index",
+                format: VertexFormat::Float1,
+                offset: offset_of!(Vertex, texture),
+            },
+        ],
+        ..Default::default()
+    }
 }
 
 const VERTEX_SHADER: &str = r#"#version 100
 attribute vec3 vertex_pos;
 attribute vec2 tex_uv;
 attribute vec4 vertex_color;
+attribute float tex_index;
 
 varying lowp vec4 fragmentColor;
 varying lowp vec2 texCoord;
+varying mediump float texIndex;
 
 uniform mat4 viewProjectionMatrix;
 
@@ -272,19 +465,42 @@ void main() {
     gl_Position = viewProjectionMatrix * vec4(vertex_pos, 1.0);
     fragmentColor = vertex_color;
     texCoord = tex_uv;
+    texIndex = tex_index;
 }
 "#;
 
-const FRAGMENT_SHADER: &str = r#"#version 100
+/// Samples the texture selected by `texIndex` from `textures` samplers. GLSL ES 1.0 can't
+/// index sampler arrays with a varying, hence the branches.
+fn fragment_shader(textures: usize) -> String {
+    let mut source = String::from(
+        "#version 100
 varying lowp vec4 fragmentColor;
 varying lowp vec2 texCoord;
+varying mediump float texIndex;
 
-uniform sampler2D Tex;
-
-void main() {
-    gl_FragColor = fragmentColor * texture2D(Tex, texCoord);
+",
+    );
+    for sampler in &SAMPLERS[..textures] {
+        source += &format!("uniform sampler2D {sampler};\n");
+    }
+    source += "\nvoid main() {\n    lowp vec4 texel;\n";
+    for (unit, sampler) in SAMPLERS[..textures].iter().enumerate() {
+        let branch = match unit {
+            0 if textures == 1 => continue,
+            0 => "    if (texIndex < 0.5) {\n".to_string(),
+            _ if unit + 1 == textures => "    } else {\n".to_string(),
+            _ => format!("    }} else if (texIndex < {unit}.5) {{\n"),
+        };
+        source += &branch;
+        source += &format!("        texel = texture2D({sampler}, texCoord);\n");
+    }
+    source += match textures {
+        1 => "    texel = texture2D(Tex, texCoord);\n",
+        _ => "    }\n",
+    };
+    source += "    gl_FragColor = fragmentColor * texel;\n}\n";
+    source
 }
-"#;
 
 #[cfg(test)]
 mod tests {
@@ -292,6 +508,6 @@ mod tests {
 
     #[test]
     fn function_name_test() {
-        assert_eq!(size_of::<Vertex>(), 24);
+        assert_eq!(size_of::<Vertex>(), 28);
     }
 }
@@ -1,5 +1,4 @@
-use crate::Context;
-use miniquad::gl::*;
+use crate::{Backend, Context, TextureFilter, TextureId};
 use std::rc::Rc;
 
 #[derive(Debug, Clone, Copy)]
@@ -8,25 +7,55 @@ pub struct Region {
     pub bottom_right: [f32; 2],
 }
 
-#[derive(Eq, PartialEq)]
-struct GLTexture(GLuint);
+struct TextureHandle {
+    id: TextureId,
+    backend: Rc<dyn Backend>,
+}
 
 #[derive(Clone)]
 pub struct Texture {
-    gl_texture: Rc<GLTexture>,
+    handle: Rc<TextureHandle>,
     pub width: u32,
     pub height: u32,
+    /// Bottom row first, like render target textures
+    flipped: bool,
+    /// No pixel is translucent
+    opaque: bool,
 }
 
 impl PartialEq for Texture {
     fn eq(&self, other: &Self) -> bool {
-        self.gl_texture == other.gl_texture
+        self.handle.id == other.handle.id
     }
 }
 
 impl Texture {
+    pub(crate) fn new(
+        backend: Rc<dyn Backend>,
+        id: TextureId,
+        width: u32,
+        height: u32,
+        flipped: bool,
+    ) -> Self {
+        Texture {
+            handle: Rc::new(TextureHandle { id, backend }),
+            width,
+            height,
+            flipped,
+            opaque: false,
+        }
+    }
+
+    pub fn id(&self) -> TextureId {
+        self.handle.id
+    }
+
+    /// Binds the texture to `GL_TEXTURE_2D` of the active texture unit. The id is only a GL
+    /// texture name with [`crate::GlBackend`], other backends must not use this.
+    #[cfg(feature = "miniquad")]
+    #[deprecated(note = "textures are bound by the Backend, pass them in a DrawCall instead")]
     pub fn bind(&self, _ctx: &Context) {
-        unsafe { glBindTexture(GL_TEXTURE_2D, self.gl_texture.0) }
+        unsafe { miniquad::gl::glBindTexture(miniquad::gl::GL_TEXTURE_2D, self.handle.id.0) }
     }
 
     pub fn as_region(&self) -> Region {
@@ -35,13 +64,23 @@ impl Texture {
             bottom_right: [self.width as f32, self.height as f32],
         }
     }
+
+    /// Whether the rows are stored bottom first, which is the case for the texture of a
+    /// [`crate::RenderTarget`]. [`crate::SpriteBatch`] takes care of it, custom shaders
+    /// have to flip the texture coordinates themselves.
+    pub fn is_flipped(&self) -> bool {
+        self.flipped
+    }
+
+    /// Whether all pixels are fully opaque, which is unknown and `false` for render targets.
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
 }
 
-impl Drop for GLTexture {
+impl Drop for TextureHandle {
     fn drop(&mut self) {
-        unsafe {
-            glDeleteTextures(1, &mut self.0);
-        }
+        self.backend.delete_texture(self.id);
     }
 }
 
@@ -49,8 +88,7 @@ pub struct TextureBuilder<'a> {
     data: &'a [u8],
     width: u32,
     height: u32,
-    mag_filter: i32,
-    min_filter: i32,
+    filter: TextureFilter,
 }
 
 impl<'a> TextureBuilder<'a> {
@@ -60,44 +98,20 @@ impl<'a> TextureBuilder<'a> {
             data,
             width,
             height,
-            mag_filter: GL_LINEAR as i32,
-            min_filter: GL_LINEAR as i32,
+            filter: TextureFilter::Linear,
         }
     }
 
-    pub fn build(self, context: &Context) -> Texture {
-        let texture = unsafe {
-            let mut texture_id = 0;
-            glGenTextures(1, &mut texture_id);
-            Texture {
-                gl_texture: Rc::new(GLTexture(texture_id)),
-                width: self.width,
-                height: self.height,
-            }
-        };
-        texture.bind(context);
-        unsafe {
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, self.mag_filter);
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, self.min_filter);
+    pub fn filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
 
-            glTexImage2D(
-                GL_TEXTURE_2D,
-                0,
-                GL_RGBA as i32,
-                self.width as i32,
-                self.height as i32,
-                0,
-                GL_RGBA,
-                GL_UNSIGNED_BYTE,
-                self.data.as_ptr() as *const GLvoid,
-            );
-            if self.width.is_power_of_two() && self.height.is_power_of_two() {
-                glGenerateMipmap(GL_TEXTURE_2D);
-            } else {
-                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as i32);
-                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as i32);
-            }
-        }
+    pub fn build(self, context: &Context) -> Texture {
+        let backend = context.backend().clone();
+        let id = backend.create_texture(self.width, self.height, self.data, self.filter);
+        let mut texture = Texture::new(backend, id, self.width, self.height, false);
+        texture.opaque = self.data.chunks_exact(4).all(|pixel| pixel[3] == 255);
         texture
     }
 }
@@ -0,0 +1,81 @@
+/// Rate at which [`crate::Application::update`] is called, independent of the frame rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedTimestep {
+    rate: f32,
+    max_steps: u32,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(60.0)
+    }
+}
+
+impl FixedTimestep {
+    /// `rate` updates per second.
+    pub fn new(rate: f32) -> Self {
+        assert!(rate > 0.0, "Update rate must be positive");
+        Self { rate, max_steps: 5 }
+    }
+
+    /// Upper bound of updates run in a single frame. If the game falls further behind,
+    /// the remaining time is dropped instead of spiraling into ever longer frames.
+    pub fn max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Seconds simulated by one update.
+    pub fn step(&self) -> f32 {
+        1.0 / self.rate
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Accumulator {
+    time: f64,
+}
+
+impl Accumulator {
+    /// Adds frame time and returns the number of fixed steps to run.
+    pub(crate) fn advance(&mut self, timestep: &FixedTimestep, delta: f32) -> u32 {
+        let step = 1.0 / timestep.rate as f64;
+        self.time += delta as f64;
+        let steps = (self.time / step).floor();
+        if steps > timestep.max_steps as f64 {
+            self.time = 0.0;
+            return timestep.max_steps;
+        }
+        self.time -= steps * step;
+        steps as u32
+    }
+
+    /// How far the simulation is into the next step, between 0 and 1.
+    pub(crate) fn alpha(&self, timestep: &FixedTimestep) -> f32 {
+        (self.time * timestep.rate as f64).clamp(0.0, 1.0) as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accumulates_partial_steps() {
+        let timestep = FixedTimestep::new(10.0);
+        let mut accumulator = Accumulator::default();
+        assert_eq!(accumulator.advance(&timestep, 0.05), 0);
+        assert!((accumulator.alpha(&timestep) - 0.5).abs() < 1e-5);
+        assert_eq!(accumulator.advance(&timestep, 0.16), 2);
+        assert!((accumulator.alpha(&timestep) - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn limits_catch_up() {
+        let timestep = FixedTimestep::new(100.0).max_steps(3);
+        let mut accumulator = Accumulator::default();
+        assert_eq!(accumulator.advance(&timestep, 1.0), 3);
+        assert_eq!(accumulator.alpha(&timestep), 0.0);
+        assert_eq!(accumulator.advance(&timestep, 0.015), 1);
+    }
+}
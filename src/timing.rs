@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+/// Frame time statistics over a sliding window of recent frames, see [`crate::Context::frame_stats`].
+pub struct FrameStats {
+    history: VecDeque<f32>,
+    window: usize,
+    sum: f64,
+    frame_count: u64,
+    elapsed: f64,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(120)
+    }
+}
+
+impl FrameStats {
+    /// `window` is the number of frames averaged and kept in the history.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "Window must contain at least one frame");
+        Self {
+            history: VecDeque::with_capacity(window),
+            window,
+            sum: 0.0,
+            frame_count: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, delta: f32) {
+        if self.history.len() == self.window {
+            self.sum -= self.history.pop_front().unwrap_or_default() as f64;
+        }
+        self.history.push_back(delta);
+        self.sum += delta as f64;
+        self.frame_count += 1;
+        self.elapsed += delta as f64;
+    }
+
+    pub(crate) fn set_window(&mut self, window: usize) {
+        assert!(window > 0, "Window must contain at least one frame");
+        self.window = window;
+        while self.history.len() > window {
+            self.sum -= self.history.pop_front().unwrap_or_default() as f64;
+        }
+    }
+
+    /// Frames per second, averaged over the window.
+    pub fn fps(&self) -> f32 {
+        let average = self.average_frame_time();
+        if average > 0.0 {
+            1.0 / average
+        } else {
+            0.0
+        }
+    }
+
+    /// Duration of the last frame in seconds.
+    pub fn frame_time(&self) -> f32 {
+        self.history.back().copied().unwrap_or_default()
+    }
+
+    /// Mean frame time in seconds over the window.
+    pub fn average_frame_time(&self) -> f32 {
+        if self.history.is_empty() {
+            0.0
+        } else {
+            (self.sum / self.history.len() as f64) as f32
+        }
+    }
+
+    /// Longest frame time in seconds within the window.
+    pub fn max_frame_time(&self) -> f32 {
+        self.history.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// Frames run since start.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Seconds since start, the sum of all frame times.
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Frame times of the window, oldest first. Handy for drawing a graph.
+    pub fn history(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.history.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sliding_window() {
+        let mut stats = FrameStats::new(3);
+        assert_eq!(stats.fps(), 0.0);
+        for delta in [0.1, 0.02, 0.02, 0.02] {
+            stats.record(delta);
+        }
+        assert_eq!(stats.frame_count(), 4);
+        assert!((stats.elapsed() - 0.16).abs() < 1e-6);
+        assert_eq!(stats.history().collect::<Vec<_>>(), vec![0.02, 0.02, 0.02]);
+        assert!((stats.fps() - 50.0).abs() < 1e-3);
+        assert_eq!(stats.max_frame_time(), 0.02);
+
+        stats.record(0.05);
+        stats.set_window(1);
+        assert_eq!(stats.history().len(), 1);
+        assert_eq!(stats.average_frame_time(), 0.05);
+        assert_eq!(stats.frame_time(), 0.05);
+    }
+}
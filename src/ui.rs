@@ -80,7 +80,7 @@ impl<T> Frame<T> {
     }
 }
 
-impl<'a, T: LayoutElement> LayoutElement for Frame<&'a T> {
+impl<T: LayoutElement> LayoutElement for Frame<&T> {
     fn render(&self, ctx: &Context, ui: &mut Ui, mouse_pos: Vec2) {
         let bl = self.xy.get();
         let tr = bl + self.wh.get();
@@ -113,7 +113,7 @@ impl<S: Borrow<str>> Button<S> {
         Self {
             xy: Cell::new(Vec2::ZERO),
             wh: Cell::new(None),
-            text: text,
+            text,
             padding: vec2(20.0, 20.0),
             pressed: Cell::new(false),
         }
@@ -152,7 +152,12 @@ impl<S: Borrow<str>> LayoutElement for Button<S> {
             .unwrap_or_else(|| dim.1 - dim.0 + self.padding);
         let xy = self.xy.get();
         let tr = xy + wh;
-        let mouse_on_button = mp.x >= xy.x && mp.x <= tr.x && mp.y >= xy.y && mp.y <= tr.y;
+        let on_button = |p: Vec2| p.x >= xy.x && p.x <= tr.x && p.y >= xy.y && p.y <= tr.y;
+        let mouse_on_button = on_button(mp);
+        let touched = ctx
+            .touches_started()
+            .iter()
+            .any(|touch| on_button(ui.window_to_ui(ctx, touch.position)));
         ui.shapes.add_filled_rect(
             ctx,
             xy,
@@ -171,7 +176,7 @@ impl<S: Borrow<str>> LayoutElement for Button<S> {
             WHITE,
         );
         self.pressed
-            .set(ctx.is_mouse_button_pressed(MouseButton::Left) && mouse_on_button);
+            .set((ctx.is_mouse_button_pressed(MouseButton::Left) && mouse_on_button) || touched);
     }
 }
 
@@ -180,7 +185,7 @@ pub struct VerticalLayout<T> {
     gap: f32,
 }
 
-impl<'a> LayoutElement for VerticalLayout<Vec<&dyn LayoutElement>> {
+impl LayoutElement for VerticalLayout<Vec<&dyn LayoutElement>> {
     fn prefered_dimensions(&self, ui: &Ui) -> Vec2 {
         let mut dim = Vec2::ZERO;
         let mut element_count: usize = 0;
@@ -224,7 +229,7 @@ pub struct CenterLayout<T> {
     element: T,
 }
 
-impl<'a, T: LayoutElement> LayoutElement for CenterLayout<T> {
+impl<T: LayoutElement> LayoutElement for CenterLayout<T> {
     fn prefered_dimensions(&self, ui: &Ui) -> Vec2 {
         self.element.prefered_dimensions(ui)
     }
@@ -279,34 +284,42 @@ impl<'a> LayoutElement for CornerLayout<'a> {
     }
 
     fn set_rect(&self, ui: &Ui, xy: Vec2, dim: Vec2) {
-        self.top_right.map(|br| {
-            let dim_top_right = br.prefered_dimensions(ui);
-            br.set_rect(ui, xy + dim - dim_top_right, dim_top_right)
-        });
-        self.bottom_right.map(|br| {
+        if let Some(tr) = self.top_right {
+            let dim_top_right = tr.prefered_dimensions(ui);
+            tr.set_rect(ui, xy + dim - dim_top_right, dim_top_right)
+        }
+        if let Some(br) = self.bottom_right {
             let dim_top_right = br.prefered_dimensions(ui);
             br.set_rect(
                 ui,
                 xy + (dim - dim_top_right) * vec2(1.0, 0.0),
                 dim_top_right,
             )
-        });
+        }
     }
 
     fn render(&self, ctx: &Context, ui: &mut Ui, mouse_pos: Vec2) {
-        self.top_right.map(|tr| tr.render(ctx, ui, mouse_pos));
-        self.bottom_right.map(|br| br.render(ctx, ui, mouse_pos));
+        if let Some(tr) = self.top_right {
+            tr.render(ctx, ui, mouse_pos);
+        }
+        if let Some(br) = self.bottom_right {
+            br.render(ctx, ui, mouse_pos);
+        }
     }
 }
 
 impl Ui {
     pub fn new(ctx: &Context, font: Font) -> Self {
-        Self {
-            shapes: ShapeBatch::new(ctx),
-            sprites: SpriteBatch::new(ctx),
+        Self::try_new(ctx, font).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_new(ctx: &Context, font: Font) -> Result<Self, OgtError> {
+        Ok(Self {
+            shapes: ShapeBatch::try_new(ctx)?,
+            sprites: SpriteBatch::try_new(ctx)?,
             font,
             ui_matrix_i: Mat4::default(),
-        }
+        })
     }
 
     pub fn screen_to_ui(&self, point: Vec2) -> Vec2 {
@@ -321,13 +334,17 @@ impl Ui {
         self.ui_matrix_i = matrix.inverse();
     }
 
-    pub fn render(&mut self, ctx: &Context, item: &impl LayoutElement) {
-        let mouse_pos = vec3(
-            ctx.mouse_position().x as f32 / ctx.screen_size().x as f32 * 2.0 - 1.0,
-            ctx.mouse_position().y as f32 / ctx.screen_size().y as f32 * -2.0 + 1.0,
+    fn window_to_ui(&self, ctx: &Context, point: Vec2) -> Vec2 {
+        let ndc = vec3(
+            point.x / ctx.screen_size().x as f32 * 2.0 - 1.0,
+            point.y / ctx.screen_size().y as f32 * -2.0 + 1.0,
             0.0,
         );
-        let mouse_pos = self.ui_matrix_i.transform_point3(mouse_pos).truncate();
+        self.ui_matrix_i.transform_point3(ndc).truncate()
+    }
+
+    pub fn render(&mut self, ctx: &Context, item: &impl LayoutElement) {
+        let mouse_pos = self.window_to_ui(ctx, ctx.mouse_position());
         item.render(ctx, self, mouse_pos);
     }
 
@@ -0,0 +1,118 @@
+use image::imageops::{resize, FilterType};
+use image::RgbaImage;
+use miniquad::conf;
+
+/// Window icon in the three sizes the platforms ask for.
+#[derive(Clone)]
+pub struct WindowIcon {
+    small: [u8; 16 * 16 * 4],
+    medium: [u8; 32 * 32 * 4],
+    big: [u8; 64 * 64 * 4],
+}
+
+impl WindowIcon {
+    /// Scales the image down (or up) to all required icon sizes.
+    pub fn from_image(image: &RgbaImage) -> Self {
+        let mut icon = WindowIcon {
+            small: [0; 16 * 16 * 4],
+            medium: [0; 32 * 32 * 4],
+            big: [0; 64 * 64 * 4],
+        };
+        for (size, target) in [
+            (16, &mut icon.small[..]),
+            (32, &mut icon.medium[..]),
+            (64, &mut icon.big[..]),
+        ] {
+            target.copy_from_slice(resize(image, size, size, FilterType::Triangle).as_raw());
+        }
+        icon
+    }
+}
+
+pub struct WindowConfig {
+    title: String,
+    width: u32,
+    height: u32,
+    fullscreen: bool,
+    resizable: bool,
+    high_dpi: bool,
+    sample_count: u32,
+    icon: Option<WindowIcon>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            width: 800,
+            height: 600,
+            fullscreen: false,
+            resizable: true,
+            high_dpi: false,
+            sample_count: 4,
+            icon: None,
+        }
+    }
+}
+
+impl WindowConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Initial window size in logical pixels.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn high_dpi(mut self, high_dpi: bool) -> Self {
+        self.high_dpi = high_dpi;
+        self
+    }
+
+    /// MSAA samples, 1 disables multisampling.
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn icon(mut self, icon: WindowIcon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    pub(crate) fn into_conf(self) -> conf::Conf {
+        conf::Conf {
+            window_title: self.title,
+            window_width: self.width as i32,
+            window_height: self.height as i32,
+            fullscreen: self.fullscreen,
+            window_resizable: self.resizable,
+            high_dpi: self.high_dpi,
+            sample_count: self.sample_count as i32,
+            icon: self.icon.map(|icon| conf::Icon {
+                small: icon.small,
+                medium: icon.medium,
+                big: icon.big,
+            }),
+            ..conf::Conf::default()
+        }
+    }
+}